@@ -1,12 +1,16 @@
 use anyhow::Result;
-use aws_sdk_sqs::types::MessageSystemAttributeName;
+use aws_sdk_sqs::types::Message;
 use clap::Parser;
 use shared::{
-    cli::{CommonArgs, merged_config, require_queue_name},
+    attrs::{self, BinaryFormat},
+    cli::{CommonArgs, ConsumerArgs, merged_config, require_queue_name},
     config::build_sqs_client,
-    logging, sqs,
+    consumer::{Ack, Consumer, ConsumerOptions, Handler},
+    encryption::Envelope,
+    logging,
+    offload::Offloader,
+    sns, sqs,
 };
-use tracing::warn;
 
 #[derive(Parser, Debug)]
 #[command(name = "recv_attrs")]
@@ -14,9 +18,82 @@ struct Args {
     #[command(flatten)]
     common: CommonArgs,
 
-    /// Do not delete messages after receiving (observe redelivery)
+    #[command(flatten)]
+    consumer: ConsumerArgs,
+
+    /// Print SNS-delivered bodies as-is instead of unwrapping the envelope
     #[arg(long)]
-    no_delete: bool,
+    raw: bool,
+
+    /// How to print Binary attribute values
+    #[arg(long, value_enum, default_value_t = BinaryFormat::Base64)]
+    binary: BinaryFormat,
+}
+
+/// Prints body, system attributes and user attributes of each message.
+struct AttrsHandler {
+    raw: bool,
+    binary: BinaryFormat,
+}
+
+impl Handler for AttrsHandler {
+    async fn handle(&self, m: &Message) -> Result<Ack> {
+        let mid = m.message_id().unwrap_or("unknown");
+        let body = m.body().unwrap_or("");
+        let envelope = sns::parse_envelope(body).filter(|_| !self.raw);
+        let body = envelope.as_ref().map_or(body, |env| env.message.as_str());
+        println!("[recv_attrs] received: message_id={} body={:?}", mid, body);
+
+        // SNS envelope metadata (only when delivered without raw delivery)
+        if let Some(env) = &envelope {
+            println!("[recv_attrs] sns: TopicArn={}", env.topic_arn);
+            if let Some(subject) = &env.subject {
+                println!("[recv_attrs] sns: Subject={}", subject);
+            }
+            println!("[recv_attrs] sns: Timestamp={}", env.timestamp);
+        }
+
+        // Print FIFO/system attributes (if present)
+        if let Some(sys) = m.attributes() {
+            if sys.is_empty() {
+                println!("[recv_attrs] system: (none)");
+            } else {
+                for (k, v) in sys {
+                    println!("[recv_attrs] system: {}={}", k, v);
+                }
+            }
+        }
+
+        // Print user attributes (if any); SNS attributes live inside the envelope
+        if let Some(env) = envelope
+            .as_ref()
+            .filter(|e| !e.message_attributes.is_empty())
+        {
+            for (k, v) in &env.message_attributes {
+                println!(
+                    "[recv_attrs] attrs: {}({})={}",
+                    k,
+                    v.data_type,
+                    attrs::render_text(&v.data_type, &v.value, self.binary)
+                );
+            }
+        } else if let Some(amap) = m.message_attributes() {
+            if amap.is_empty() {
+                println!("[recv_attrs] attrs: (none)");
+            } else {
+                for (k, v) in amap {
+                    println!(
+                        "[recv_attrs] attrs: {}({})={}",
+                        k,
+                        v.data_type(),
+                        attrs::render(v, self.binary)
+                    );
+                }
+            }
+        }
+
+        Ok(Ack::Delete)
+    }
 }
 
 #[tokio::main]
@@ -31,80 +108,33 @@ async fn main() -> Result<()> {
     let qname = require_queue_name(&args.common, &cfg)?;
     let url = sqs::get_queue_url(&client, &qname).await?;
 
-    let wait_secs = cfg.recv_wait_secs();
+    let opts = ConsumerOptions::from_args("recv_attrs", &cfg, &args.consumer)?;
 
     println!(
-        "[recv_attrs] region={} queue={} mode={:?} wait={}s delete={}",
-        cfg.runtime.region, qname, cfg.runtime.mode, wait_secs, !args.no_delete
+        "[recv_attrs] region={} queue={} mode={:?} wait={}s delete={} batch={}",
+        cfg.runtime.region,
+        qname,
+        cfg.runtime.mode,
+        opts.wait_secs,
+        !opts.no_delete,
+        opts.batch_size
     );
-    println!("[recv_attrs] waiting for messages... (Ctrl+C to stop)");
-
-    loop {
-        let out = client
-            .receive_message()
-            .queue_url(&url)
-            .max_number_of_messages(1)
-            .wait_time_seconds(wait_secs)
-            // request *all* user attributes
-            .message_attribute_names("All")
-            // request system attrs (e.g., SequenceNumber, MessageGroupId for FIFO)
-            .message_system_attribute_names(MessageSystemAttributeName::All)
-            .send()
-            .await?;
-
-        let msgs = out.messages();
-        if msgs.is_empty() {
-            continue;
-        }
-
-        for m in msgs {
-            let mid = m.message_id().unwrap_or("unknown");
-            let body = m.body().unwrap_or("");
-            println!("[recv_attrs] received: message_id={} body={:?}", mid, body);
-
-            // Print FIFO/system attributes (if present)
-            if let Some(sys) = m.attributes() {
-                if sys.is_empty() {
-                    println!("[recv_attrs] system: (none)");
-                } else {
-                    for (k, v) in sys {
-                        println!("[recv_attrs] system: {}={}", k, v);
-                    }
-                }
-            }
 
-            // Print user attributes (if any)
-            if let Some(amap) = m.message_attributes() {
-                if amap.is_empty() {
-                    println!("[recv_attrs] attrs: (none)");
-                } else {
-                    for (k, v) in amap {
-                        let dt = v.data_type();
-                        if let Some(s) = v.string_value() {
-                            println!("[recv_attrs] attrs: {}({})={:?}", k, dt, s);
-                        } else {
-                            // For non-string/binary types, print a generic line
-                            println!("[recv_attrs] attrs: {}({})", k, dt);
-                        }
-                    }
-                }
-            }
+    let offload = Offloader::from_config(&cfg).await?;
+    let encryption = Envelope::from_config(&cfg).await?;
+    let consumer = Consumer::new(
+        client,
+        url,
+        AttrsHandler {
+            raw: args.raw,
+            binary: args.binary,
+        },
+        opts,
+    )
+    .with_offload(offload)
+    .with_encryption(encryption);
+    consumer.print_settings();
+    println!("[recv_attrs] waiting for messages... (Ctrl+C to stop)");
 
-            // Delete unless told not to
-            if args.no_delete {
-                warn!("--no-delete set; not deleting message_id={}", mid);
-            } else if let Some(rh) = m.receipt_handle() {
-                println!("[recv_attrs] deleting...");
-                client
-                    .delete_message()
-                    .queue_url(&url)
-                    .receipt_handle(rh)
-                    .send()
-                    .await?;
-                println!("[recv_attrs] deleted message_id={}", mid);
-            } else {
-                warn!("missing receipt_handle; cannot delete");
-            }
-        }
-    }
+    consumer.run().await
 }
@@ -0,0 +1,99 @@
+use anyhow::Result;
+use aws_sdk_sqs::types::Message;
+use clap::{Parser, ValueEnum};
+use lab2_message_attributes_fifo::UserEvent;
+use shared::{
+    cli::{CommonArgs, ConsumerArgs, merged_config, require_queue_name},
+    config::build_sqs_client,
+    consumer::{Ack, ConsumerOptions},
+    encryption::Envelope,
+    logging,
+    offload::Offloader,
+    sqs,
+    typed::{self, Consumer, PoisonAction, TypedHandler},
+};
+
+#[derive(Parser, Debug)]
+#[command(name = "recv_events")]
+struct Args {
+    #[command(flatten)]
+    common: CommonArgs,
+
+    #[command(flatten)]
+    consumer: ConsumerArgs,
+
+    /// What to do with bodies that are not a valid UserEvent
+    #[arg(long, value_enum, default_value_t = Poison::Leave)]
+    poison: Poison,
+
+    /// Queue poison messages are forwarded to (with --poison forward)
+    #[arg(long, value_name = "QUEUE", required_if_eq("poison", "forward"))]
+    poison_queue: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Poison {
+    /// Leave for redelivery (ends up in the DLQ if one is configured)
+    Leave,
+    /// Delete right away
+    Delete,
+    /// Move to --poison-queue
+    Forward,
+}
+
+/// Prints each decoded event.
+struct PrintEvents;
+
+impl TypedHandler<UserEvent> for PrintEvents {
+    async fn handle(&self, event: UserEvent, m: &Message) -> Result<Ack> {
+        println!(
+            "[recv_events] received: message_id={} user={} action={} tenant={}",
+            m.message_id().unwrap_or("unknown"),
+            event.user_id,
+            event.action,
+            event.tenant.as_deref().unwrap_or("-")
+        );
+        Ok(Ack::Delete)
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    logging::init();
+    let args = Args::parse();
+
+    let default_lab_cfg = format!("{}/config.toml", env!("CARGO_MANIFEST_DIR"));
+    let cfg = merged_config(&args.common, &default_lab_cfg)?;
+    let client = build_sqs_client(&cfg).await?;
+    let qname = require_queue_name(&args.common, &cfg)?;
+    let url = sqs::get_queue_url(&client, &qname).await?;
+
+    let poison = match args.poison {
+        Poison::Leave => PoisonAction::Leave,
+        Poison::Delete => PoisonAction::Delete,
+        Poison::Forward => {
+            let name = args.poison_queue.as_deref().unwrap_or_default();
+            PoisonAction::Forward {
+                queue_url: sqs::get_queue_url(&client, name).await?,
+                client: client.clone(),
+            }
+        }
+    };
+
+    let opts = ConsumerOptions::from_args("recv_events", &cfg, &args.consumer)?;
+    let message_type = typed::default_message_type::<UserEvent>();
+    println!(
+        "[recv_events] region={} queue={} message-type={} poison={:?}",
+        cfg.runtime.region, qname, message_type, args.poison
+    );
+
+    let offload = Offloader::from_config(&cfg).await?;
+    let encryption = Envelope::from_config(&cfg).await?;
+    let consumer = Consumer::with_poison(client, url, PrintEvents, poison, message_type, opts)
+        .with_offload(offload)
+        .with_encryption(encryption);
+    consumer.print_settings();
+    println!("[recv_events] waiting for messages... (Ctrl+C to stop)");
+
+    consumer.run().await
+}
@@ -1,10 +1,12 @@
-use anyhow::{Result, anyhow};
-use aws_sdk_sqs::types::MessageAttributeValue;
+use anyhow::{Context, Result, anyhow};
 use clap::Parser;
 use shared::{
+    attrs,
+    batch::{self, BatchEntry},
     cli::{CommonArgs, merged_config, require_queue_name},
     config::build_sqs_client,
     logging, sqs,
+    validate::{self, QueueKind},
 };
 
 #[derive(Parser, Debug)]
@@ -20,8 +22,9 @@ struct Args {
     /// Positional message (fallback)
     message: Option<String>,
 
-    /// Add attribute as key=value (repeatable)
-    /// Example: --attr event_type=user.created --attr tenant=acme
+    /// Add attribute as key=value or key:Type=value (repeatable, at most 10)
+    /// Type is String, Number or Binary (base64 or @file), optionally with a custom suffix.
+    /// Example: --attr event_type=user.created --attr retries:Number=3 --attr blob:Binary=@payload.bin
     #[arg(long = "attr")]
     attrs: Vec<String>,
 
@@ -32,16 +35,14 @@ struct Args {
     /// For FIFO queues: MessageDeduplicationId (optional)
     #[arg(long, value_name = "DEDUP_ID")]
     dedup: Option<String>,
-}
 
-fn parse_attr(kv: &str) -> Result<(String, String)> {
-    let (k, v) = kv
-        .split_once('=')
-        .ok_or_else(|| anyhow!("Invalid --attr '{}'. Use key=value.", kv))?;
-    if k.is_empty() {
-        return Err(anyhow!("Attribute key cannot be empty"));
-    }
-    Ok((k.to_string(), v.to_string()))
+    /// Send N messages via SendMessageBatch (bodies get a " #i" suffix, dedup ids a "-i" suffix)
+    #[arg(long, value_name = "N", conflicts_with = "from_file")]
+    count: Option<usize>,
+
+    /// Send every line of a JSONL file via SendMessageBatch; --attr/--group apply as defaults
+    #[arg(long, value_name = "PATH")]
+    from_file: Option<String>,
 }
 
 #[tokio::main]
@@ -54,36 +55,23 @@ async fn main() -> Result<()> {
     let cfg = merged_config(&args.common, &default_lab_cfg)?;
     let client = build_sqs_client(&cfg).await?;
     let qname = require_queue_name(&args.common, &cfg)?;
-    let url = sqs::get_queue_url(&client, &qname).await?;
 
     let body = args.msg.or(args.message).unwrap_or_else(|| "hello".into());
 
-    // Start request
-    let mut req = client.send_message().queue_url(&url).message_body(body);
-
-    // Add attributes (String type)
-    for kv in &args.attrs {
-        let (k, v) = parse_attr(kv)?;
-        let mval = MessageAttributeValue::builder()
-            .data_type("String")
-            .string_value(v)
-            .build()?;
-        req = req.message_attributes(k, mval);
-    }
+    // Attributes (typed, validated before sending)
+    let attributes = attrs::parse_all(&args.attrs)?;
 
     // Determine if this is a FIFO queue
     let is_fifo = qname.ends_with(".fifo") || cfg.sqs.fifo.unwrap_or(false);
+    let kind = if is_fifo {
+        QueueKind::Fifo
+    } else {
+        QueueKind::Standard
+    };
 
     // FIFO-only fields
-    if is_fifo {
-        let group = args
-            .group
-            .clone()
-            .ok_or_else(|| anyhow!("This queue is FIFO; --group <MessageGroupId> is required."))?;
-        req = req.message_group_id(group);
-        if let Some(d) = args.dedup {
-            req = req.message_deduplication_id(d);
-        }
+    let (group, dedup) = if is_fifo {
+        (args.group, args.dedup)
     } else {
         // On Standard queues, ignore FIFO flags if provided
         if args.group.is_some() || args.dedup.is_some() {
@@ -92,9 +80,84 @@ async fn main() -> Result<()> {
                 qname
             );
         }
+        (None, None)
+    };
+
+    // Batch mode
+    let entries: Option<Vec<BatchEntry>> = match (args.count, &args.from_file) {
+        (Some(0), _) => return Err(anyhow!("--count must be at least 1")),
+        (Some(n), _) => Some(
+            (0..n)
+                .map(|i| BatchEntry {
+                    body: format!("{body} #{i}"),
+                    attributes: attributes.clone(),
+                    group_id: group.clone(),
+                    dedup_id: dedup.as_ref().map(|d| format!("{d}-{i}")),
+                })
+                .collect(),
+        ),
+        (None, Some(path)) => {
+            let mut out = Vec::new();
+            for fe in batch::load_jsonl(path)? {
+                let line = fe.line;
+                let mut e = fe.into_entry()?;
+                for (k, v) in &attributes {
+                    e.attributes.entry(k.clone()).or_insert_with(|| v.clone());
+                }
+                attrs::validate(&e.attributes)
+                    .with_context(|| format!("{} line {}", path, line))?;
+                if is_fifo {
+                    e.group_id = e.group_id.or_else(|| group.clone());
+                } else {
+                    e.group_id = None;
+                    e.dedup_id = None;
+                }
+                out.push(e);
+            }
+            Some(out)
+        }
+        (None, None) => None,
+    };
+    if let Some(entries) = entries {
+        if is_fifo && entries.iter().any(|e| e.group_id.is_none()) {
+            return Err(anyhow!(
+                "This queue is FIFO; pass --group or set \"group\" on every line."
+            ));
+        }
+        validate::check_entries(&entries, kind, validate::MAX_MESSAGE_BYTES)?;
+        let url = sqs::get_queue_url(&client, &qname).await?;
+        let outcomes = batch::send_all(&client, &url, &entries).await?;
+        let failed = batch::print_outcomes("send_attrs", &outcomes);
+        if failed > 0 {
+            return Err(anyhow!("{} of {} messages failed", failed, entries.len()));
+        }
+        return Ok(());
     }
 
-    let out = req.send().await?;
+    if is_fifo && group.is_none() {
+        return Err(anyhow!(
+            "This queue is FIFO; --group <MessageGroupId> is required."
+        ));
+    }
+    let entry = BatchEntry {
+        body,
+        attributes,
+        group_id: group,
+        dedup_id: dedup,
+    };
+    // Validate locally before any network call
+    validate::check_entry(&entry, kind)?;
+    let url = sqs::get_queue_url(&client, &qname).await?;
+
+    let out = client
+        .send_message()
+        .queue_url(&url)
+        .message_body(entry.body)
+        .set_message_attributes((!entry.attributes.is_empty()).then_some(entry.attributes))
+        .set_message_group_id(entry.group_id)
+        .set_message_deduplication_id(entry.dedup_id)
+        .send()
+        .await?;
     let id = out.message_id().unwrap_or("unknown");
     println!("[send_attrs] sent message_id={}", id);
     Ok(())
@@ -0,0 +1,77 @@
+use anyhow::Result;
+use clap::Parser;
+use lab2_message_attributes_fifo::UserEvent;
+use shared::{
+    cli::{CommonArgs, merged_config, require_queue_name},
+    config::build_sqs_client,
+    encryption::Envelope,
+    logging,
+    offload::Offloader,
+    sqs,
+    typed::Producer,
+};
+
+#[derive(Parser, Debug)]
+#[command(name = "send_event")]
+struct Args {
+    #[command(flatten)]
+    common: CommonArgs,
+
+    /// User the event is about
+    #[arg(long)]
+    user: String,
+
+    /// What happened (created, updated, deleted, ...)
+    #[arg(long, default_value = "created")]
+    action: String,
+
+    #[arg(long)]
+    tenant: Option<String>,
+
+    /// For FIFO queues: MessageGroupId (defaults to the user id, so each user stays ordered)
+    #[arg(long, value_name = "GROUP")]
+    group: Option<String>,
+
+    /// For FIFO queues: MessageDeduplicationId (optional)
+    #[arg(long, value_name = "DEDUP_ID")]
+    dedup: Option<String>,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    logging::init();
+    let args = Args::parse();
+
+    let default_lab_cfg = format!("{}/config.toml", env!("CARGO_MANIFEST_DIR"));
+    let cfg = merged_config(&args.common, &default_lab_cfg)?;
+    let client = build_sqs_client(&cfg).await?;
+    let qname = require_queue_name(&args.common, &cfg)?;
+    let url = sqs::get_queue_url(&client, &qname).await?;
+
+    let event = UserEvent {
+        user_id: args.user,
+        action: args.action,
+        tenant: args.tenant,
+    };
+    let offload = Offloader::from_config(&cfg).await?;
+    let encryption = Envelope::from_config(&cfg).await?;
+    let producer = Producer::<UserEvent>::new(client, url)
+        .with_offload(offload)
+        .with_encryption(encryption);
+
+    let id = if qname.ends_with(".fifo") {
+        let group = args.group.as_deref().unwrap_or(&event.user_id);
+        producer
+            .send_to_group(&event, group, args.dedup.as_deref())
+            .await?
+    } else {
+        producer.send(&event).await?
+    };
+    println!(
+        "[send_event] sent message_id={} message-type={} event={:?}",
+        id,
+        producer.message_type(),
+        event
+    );
+    Ok(())
+}
@@ -1,9 +1,11 @@
 use anyhow::{anyhow, Result};
 use clap::Parser;
 use shared::{
+    batch::BatchEntry,
     cli::{merged_config, require_queue_name, CommonArgs},
     config::build_sqs_client,
     logging, sqs,
+    validate::{self, QueueKind},
 };
 
 #[derive(Parser, Debug)]
@@ -47,20 +49,25 @@ async fn main() -> Result<()> {
         ));
     }
 
-    let url = sqs::get_queue_url(&client, &qname).await?;
     let body = args.msg.or(args.message).unwrap_or_else(|| "hello".into());
+    let entry = BatchEntry {
+        body,
+        group_id: Some(args.group),
+        dedup_id: args.dedup,
+        ..Default::default()
+    };
+    // Validate locally before any network call
+    validate::check_entry(&entry, QueueKind::Fifo)?;
+    let url = sqs::get_queue_url(&client, &qname).await?;
 
-    let mut req = client
+    let out = client
         .send_message()
         .queue_url(&url)
-        .message_body(body)
-        .message_group_id(args.group);
-
-    if let Some(d) = args.dedup {
-        req = req.message_deduplication_id(d);
-    }
-
-    let out = req.send().await?;
+        .message_body(entry.body)
+        .set_message_group_id(entry.group_id)
+        .set_message_deduplication_id(entry.dedup_id)
+        .send()
+        .await?;
     let id = out.message_id().unwrap_or("unknown");
     // SequenceNumber is present for FIFO; don’t fail if missing.
     let seq = out.sequence_number().unwrap_or("-");
@@ -0,0 +1,12 @@
+//! Typed events exchanged by the Lab 2 `send_event` / `recv_events` binaries.
+
+use serde::{Deserialize, Serialize};
+
+/// A user lifecycle event; sent as a JSON body with `message-type=UserEvent`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserEvent {
+    pub user_id: String,
+    /// e.g. "created", "updated", "deleted"
+    pub action: String,
+    pub tenant: Option<String>,
+}
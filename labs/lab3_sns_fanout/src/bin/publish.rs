@@ -0,0 +1,90 @@
+use anyhow::{Result, anyhow};
+use clap::Parser;
+use shared::{
+    attrs,
+    batch::BatchEntry,
+    cli::{CommonArgs, merged_config, require_topic_name},
+    logging,
+    sns::{self, build_sns_client},
+};
+
+#[derive(Parser, Debug)]
+#[command(name = "publish")]
+struct Args {
+    #[command(flatten)]
+    common: CommonArgs,
+
+    /// Message body (use --msg "text") or provide as positional
+    #[arg(long)]
+    msg: Option<String>,
+
+    /// Positional message (fallback)
+    message: Option<String>,
+
+    /// Optional SNS Subject (shown in the envelope)
+    #[arg(long)]
+    subject: Option<String>,
+
+    /// Add attribute as key=value or key:Type=value (repeatable)
+    /// Example: --attr event_type=user.created --attr amount:Number=42
+    #[arg(long = "attr")]
+    attrs: Vec<String>,
+
+    /// For FIFO topics: MessageGroupId
+    #[arg(long, value_name = "GROUP")]
+    group: Option<String>,
+
+    /// For FIFO topics: MessageDeduplicationId (optional with content-based dedup)
+    #[arg(long, value_name = "DEDUP_ID")]
+    dedup: Option<String>,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    logging::init();
+    let args = Args::parse();
+
+    // Merge configs and resolve topic ARN
+    let default_lab_cfg = format!("{}/config.toml", env!("CARGO_MANIFEST_DIR"));
+    let cfg = merged_config(&args.common, &default_lab_cfg)?;
+    let client = build_sns_client(&cfg).await?;
+    let topic = require_topic_name(&args.common, &cfg)?;
+    let topic_arn = sns::get_topic_arn(&client, &topic).await?;
+
+    let body = args.msg.or(args.message).unwrap_or_else(|| "hello".into());
+
+    // Typed attributes (String by default); SNS copies them to every subscriber
+    let mut entry = BatchEntry {
+        body,
+        attributes: attrs::parse_all(&args.attrs)?,
+        ..Default::default()
+    };
+
+    // Determine if this is a FIFO topic
+    let is_fifo = topic.ends_with(".fifo") || cfg.sns.fifo.unwrap_or(false);
+
+    // FIFO-only fields
+    if is_fifo {
+        let group = args
+            .group
+            .clone()
+            .ok_or_else(|| anyhow!("This topic is FIFO; --group <MessageGroupId> is required."))?;
+        entry.group_id = Some(group);
+        entry.dedup_id = args.dedup;
+    } else if args.group.is_some() || args.dedup.is_some() {
+        eprintln!(
+            "[publish] Warning: --group/--dedup ignored because {} is a Standard topic",
+            topic
+        );
+    }
+
+    let out = sns::publish(&client, &topic_arn, &entry, args.subject.as_deref()).await?;
+    let id = &out.message_id;
+    // SequenceNumber is present for FIFO; don’t fail if missing.
+    let seq = out.sequence_number.as_deref().unwrap_or("-");
+    println!(
+        "[publish] topic={} sent message_id={} sequence={}",
+        topic, id, seq
+    );
+    Ok(())
+}
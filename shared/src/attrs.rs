@@ -0,0 +1,231 @@
+//! Message attribute parsing, validation and rendering for all SQS data types:
+//! `String`, `Number`, `Binary` and custom suffixes such as `Number.float`.
+
+use std::collections::HashMap;
+
+use anyhow::{Context, Result, anyhow, bail};
+use aws_sdk_sqs::primitives::Blob;
+use aws_sdk_sqs::types::MessageAttributeValue;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+
+/// SQS accepts at most 10 message attributes per message.
+pub const MAX_ATTRIBUTES: usize = 10;
+/// Attribute names and data types are limited to 256 characters.
+pub const MAX_NAME_LEN: usize = 256;
+/// Prefixes reserved by AWS (matched case-insensitively).
+pub const RESERVED_PREFIXES: [&str; 2] = ["AWS.", "Amazon."];
+/// Numbers can have up to 38 digits of precision.
+const MAX_NUMBER_DIGITS: usize = 38;
+
+/// How Binary values are printed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum BinaryFormat {
+    #[default]
+    Base64,
+    Hex,
+}
+
+/// Check an attribute name: `A-Za-z0-9_-.`, no leading/trailing or double `.`,
+/// at most 256 characters and no reserved prefix.
+pub fn validate_name(name: &str) -> Result<()> {
+    if name.is_empty() {
+        bail!("Attribute name cannot be empty");
+    }
+    if let Some(c) = name
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.')))
+    {
+        bail!(
+            "Attribute name '{}' contains invalid character {:?}",
+            name,
+            c
+        );
+    }
+    if name.len() > MAX_NAME_LEN {
+        bail!(
+            "Attribute name '{}...' is longer than {} characters",
+            &name[..32],
+            MAX_NAME_LEN
+        );
+    }
+    if name.starts_with('.') || name.ends_with('.') || name.contains("..") {
+        bail!(
+            "Attribute name '{}' cannot start or end with '.' or contain '..'",
+            name
+        );
+    }
+    if let Some(prefix) = RESERVED_PREFIXES.iter().find(|p| {
+        name.to_ascii_lowercase()
+            .starts_with(&p.to_ascii_lowercase())
+    }) {
+        bail!(
+            "Attribute name '{}' uses the reserved prefix '{}'",
+            name,
+            prefix
+        );
+    }
+    Ok(())
+}
+
+/// Split a data type into its base (`String`, `Number`, `Binary`) and optional custom label.
+pub fn split_data_type(data_type: &str) -> Result<(&str, Option<&str>)> {
+    let (base, custom) = match data_type.split_once('.') {
+        Some((base, custom)) => (base, Some(custom)),
+        None => (data_type, None),
+    };
+    if !matches!(base, "String" | "Number" | "Binary") {
+        bail!(
+            "Unknown attribute type '{}'. Use String, Number or Binary, optionally with a .suffix",
+            data_type
+        );
+    }
+    if data_type.len() > MAX_NAME_LEN {
+        bail!("Attribute type is longer than {} characters", MAX_NAME_LEN);
+    }
+    if let Some(custom) = custom
+        && (custom.is_empty()
+            || !custom
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.')))
+    {
+        bail!("Invalid custom type suffix in '{}'", data_type);
+    }
+    Ok((base, custom))
+}
+
+/// Check a Number value: a decimal with at most 38 significant digits.
+pub fn validate_number(value: &str) -> Result<()> {
+    let n: f64 = value
+        .trim()
+        .parse()
+        .map_err(|_| anyhow!("'{}' is not a number", value))?;
+    if !n.is_finite() {
+        bail!("'{}' is not a finite number", value);
+    }
+    let digits = value
+        .trim_start_matches(['-', '+'])
+        .split(['e', 'E'])
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .filter(char::is_ascii_digit)
+        .skip_while(|c| *c == '0')
+        .count();
+    if digits > MAX_NUMBER_DIGITS {
+        bail!(
+            "'{}' has more than {} digits of precision",
+            value,
+            MAX_NUMBER_DIGITS
+        );
+    }
+    Ok(())
+}
+
+/// Build a typed attribute value. Binary values are `@path` (file contents) or base64.
+pub fn build_value(data_type: &str, raw: &str) -> Result<MessageAttributeValue> {
+    let (base, _) = split_data_type(data_type)?;
+    let builder = MessageAttributeValue::builder().data_type(data_type);
+    let builder = match base {
+        "Number" => {
+            validate_number(raw)?;
+            builder.string_value(raw.trim())
+        }
+        "Binary" => {
+            let bytes = match raw.strip_prefix('@') {
+                Some(path) => std::fs::read(path).with_context(|| format!("reading {path}"))?,
+                None => BASE64
+                    .decode(raw)
+                    .map_err(|e| anyhow!("Binary value is not valid base64 ({e}); use @file"))?,
+            };
+            builder.binary_value(Blob::new(bytes))
+        }
+        _ => builder.string_value(raw),
+    };
+    Ok(builder.build()?)
+}
+
+/// Parse `key=value` or `key:Type=value` (e.g. `count:Number=42`, `blob:Binary=@file.bin`,
+/// `price:Number.float=9.99`, `doc:String.json={"a":1}`).
+pub fn parse(spec: &str) -> Result<(String, MessageAttributeValue)> {
+    let (k, v) = spec.split_once('=').ok_or_else(|| {
+        anyhow!(
+            "Invalid --attr '{}'. Use key=value or key:Type=value.",
+            spec
+        )
+    })?;
+    let (k, dt) = k.split_once(':').unwrap_or((k, "String"));
+    validate_name(k)?;
+    let value = build_value(dt, v).with_context(|| format!("attribute '{}'", k))?;
+    Ok((k.to_string(), value))
+}
+
+/// Parse repeated `--attr` flags, rejecting duplicates and more than 10 attributes.
+pub fn parse_all(specs: &[String]) -> Result<HashMap<String, MessageAttributeValue>> {
+    let mut out = HashMap::new();
+    for spec in specs {
+        let (k, v) = parse(spec)?;
+        if out.insert(k.clone(), v).is_some() {
+            bail!("Attribute '{}' given more than once", k);
+        }
+    }
+    validate(&out)?;
+    Ok(out)
+}
+
+/// Validate a complete attribute map: count, names and data types.
+pub fn validate(attrs: &HashMap<String, MessageAttributeValue>) -> Result<()> {
+    if attrs.len() > MAX_ATTRIBUTES {
+        bail!(
+            "{} message attributes given; SQS allows at most {}",
+            attrs.len(),
+            MAX_ATTRIBUTES
+        );
+    }
+    for (k, v) in attrs {
+        validate_name(k)?;
+        split_data_type(v.data_type()).with_context(|| format!("attribute '{}'", k))?;
+    }
+    Ok(())
+}
+
+/// Render a value by type: strings quoted, numbers as-is, binary as `len=N base64=...`.
+pub fn render(v: &MessageAttributeValue, binary: BinaryFormat) -> String {
+    match v.binary_value() {
+        Some(blob) => render_binary(blob.as_ref(), binary),
+        None => render_string(v.data_type(), v.string_value().unwrap_or_default()),
+    }
+}
+
+/// Render a value delivered as text, e.g. inside an SNS envelope where Binary is base64.
+pub fn render_text(data_type: &str, value: &str, binary: BinaryFormat) -> String {
+    if data_type.starts_with("Binary") {
+        match BASE64.decode(value) {
+            Ok(bytes) => render_binary(&bytes, binary),
+            Err(_) => format!("{:?} (invalid base64)", value),
+        }
+    } else {
+        render_string(data_type, value)
+    }
+}
+
+fn render_string(data_type: &str, value: &str) -> String {
+    if data_type.starts_with("Number") {
+        match value.parse::<f64>() {
+            Ok(n) if n.to_string() != value => format!("{} (= {})", value, n),
+            _ => value.to_string(),
+        }
+    } else {
+        format!("{:?}", value)
+    }
+}
+
+fn render_binary(bytes: &[u8], format: BinaryFormat) -> String {
+    match format {
+        BinaryFormat::Base64 => format!("len={} base64={}", bytes.len(), BASE64.encode(bytes)),
+        BinaryFormat::Hex => {
+            let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+            format!("len={} hex={}", bytes.len(), hex)
+        }
+    }
+}
@@ -0,0 +1,289 @@
+//! SendMessageBatch / DeleteMessageBatch helpers: chunking, partial-failure
+//! handling and JSONL input.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use aws_sdk_sqs::Client;
+use aws_sdk_sqs::types::{
+    DeleteMessageBatchRequestEntry, MessageAttributeValue, SendMessageBatchRequestEntry,
+};
+use serde::Deserialize;
+use tracing::warn;
+
+/// SQS limits for a single SendMessageBatch call.
+pub const MAX_BATCH_ENTRIES: usize = 10;
+pub const MAX_BATCH_BYTES: usize = 256 * 1024;
+
+/// Attempts per entry; only entries reported as failed (and not sender faults) are retried.
+pub const MAX_ATTEMPTS: usize = 3;
+
+/// One message to send as part of a batch.
+#[derive(Debug, Clone, Default)]
+pub struct BatchEntry {
+    pub body: String,
+    pub attributes: HashMap<String, MessageAttributeValue>,
+    pub group_id: Option<String>,
+    pub dedup_id: Option<String>,
+}
+
+impl BatchEntry {
+    /// Payload size as SQS counts it: body plus attribute names, types and values.
+    pub fn size(&self) -> usize {
+        let attrs: usize = self
+            .attributes
+            .iter()
+            .map(|(k, v)| {
+                k.len()
+                    + v.data_type().len()
+                    + v.string_value().map_or(0, str::len)
+                    + v.binary_value().map_or(0, |b| b.as_ref().len())
+            })
+            .sum();
+        self.body.len() + attrs
+    }
+}
+
+/// Result of one entry, keyed by its position in the input.
+#[derive(Debug, Clone)]
+pub enum EntryOutcome {
+    Sent {
+        message_id: String,
+        sequence_number: Option<String>,
+    },
+    Failed {
+        code: String,
+        message: String,
+        sender_fault: bool,
+    },
+}
+
+/// Line format for `--from-file` JSONL input.
+#[derive(Debug, Clone, Deserialize)]
+pub struct FileEntry {
+    /// 1-based line number in the file, for error messages
+    #[serde(skip)]
+    pub line: usize,
+    pub body: String,
+    #[serde(default)]
+    pub attrs: HashMap<String, String>,
+    pub group: Option<String>,
+    pub dedup: Option<String>,
+}
+
+impl FileEntry {
+    /// Convert into a batch entry; file attributes are sent as `String` attributes.
+    pub fn into_entry(self) -> Result<BatchEntry> {
+        let mut attributes = HashMap::new();
+        for (k, v) in self.attrs {
+            let mval = MessageAttributeValue::builder()
+                .data_type("String")
+                .string_value(v)
+                .build()?;
+            attributes.insert(k, mval);
+        }
+        Ok(BatchEntry {
+            body: self.body,
+            attributes,
+            group_id: self.group,
+            dedup_id: self.dedup,
+        })
+    }
+}
+
+/// Parse a JSONL file; blank lines are skipped, any other line must be a
+/// [`FileEntry`] object.
+pub fn load_jsonl(path: &str) -> Result<Vec<FileEntry>> {
+    let raw = std::fs::read_to_string(path).with_context(|| format!("reading {path}"))?;
+    raw.lines()
+        .enumerate()
+        .filter(|(_, l)| !l.trim().is_empty())
+        .map(|(i, line)| {
+            let mut entry = serde_json::from_str::<FileEntry>(line)
+                .with_context(|| format!("{} line {}", path, i + 1))?;
+            entry.line = i + 1;
+            Ok(entry)
+        })
+        .collect()
+}
+
+/// Split entry indices into batches of at most 10 entries and 256 KiB.
+/// An entry that alone exceeds the byte limit gets its own batch (SQS will reject it).
+pub fn chunk(entries: &[BatchEntry], indices: &[usize]) -> Vec<Vec<usize>> {
+    let mut batches = Vec::new();
+    let mut current: Vec<usize> = Vec::new();
+    let mut bytes = 0;
+
+    for &i in indices {
+        let size = entries[i].size();
+        if !current.is_empty()
+            && (current.len() == MAX_BATCH_ENTRIES || bytes + size > MAX_BATCH_BYTES)
+        {
+            batches.push(std::mem::take(&mut current));
+            bytes = 0;
+        }
+        current.push(i);
+        bytes += size;
+    }
+    if !current.is_empty() {
+        batches.push(current);
+    }
+    batches
+}
+
+/// Send all entries with SendMessageBatch, retrying only failed entries.
+/// Returns one outcome per input entry, in input order.
+pub async fn send_all(
+    client: &Client,
+    queue_url: &str,
+    entries: &[BatchEntry],
+) -> Result<Vec<EntryOutcome>> {
+    let mut outcomes: Vec<Option<EntryOutcome>> = vec![None; entries.len()];
+    let mut pending: Vec<usize> = (0..entries.len()).collect();
+
+    for attempt in 1..=MAX_ATTEMPTS {
+        let mut retry = Vec::new();
+
+        for batch in chunk(entries, &pending) {
+            let mut req = client.send_message_batch().queue_url(queue_url);
+            for &i in &batch {
+                let e = &entries[i];
+                let entry = SendMessageBatchRequestEntry::builder()
+                    .id(i.to_string())
+                    .message_body(&e.body)
+                    .set_message_attributes(
+                        (!e.attributes.is_empty()).then(|| e.attributes.clone()),
+                    )
+                    .set_message_group_id(e.group_id.clone())
+                    .set_message_deduplication_id(e.dedup_id.clone())
+                    .build()?;
+                req = req.entries(entry);
+            }
+
+            let out = req.send().await.context("send_message_batch")?;
+
+            for ok in out.successful() {
+                let i: usize = ok.id().parse().context("unexpected batch entry id")?;
+                outcomes[i] = Some(EntryOutcome::Sent {
+                    message_id: ok.message_id().to_string(),
+                    sequence_number: ok.sequence_number().map(|s| s.to_string()),
+                });
+            }
+            for failed in out.failed() {
+                let i: usize = failed.id().parse().context("unexpected batch entry id")?;
+                if !failed.sender_fault() && attempt < MAX_ATTEMPTS {
+                    warn!(
+                        "entry {} failed ({}), retrying (attempt {}/{})",
+                        i,
+                        failed.code(),
+                        attempt + 1,
+                        MAX_ATTEMPTS
+                    );
+                    retry.push(i);
+                }
+                outcomes[i] = Some(EntryOutcome::Failed {
+                    code: failed.code().to_string(),
+                    message: failed.message().unwrap_or_default().to_string(),
+                    sender_fault: failed.sender_fault(),
+                });
+            }
+        }
+
+        if retry.is_empty() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(200 * attempt as u64)).await;
+        pending = retry;
+    }
+
+    Ok(outcomes
+        .into_iter()
+        .map(|o| {
+            o.unwrap_or(EntryOutcome::Failed {
+                code: "Missing".into(),
+                message: "entry not reported in batch response".into(),
+                sender_fault: false,
+            })
+        })
+        .collect())
+}
+
+/// Print one line per entry plus a summary, prefixed with `tag` (e.g. "send").
+/// Returns the number of failed entries.
+pub fn print_outcomes(tag: &str, outcomes: &[EntryOutcome]) -> usize {
+    let mut failed = 0;
+    for (i, o) in outcomes.iter().enumerate() {
+        match o {
+            EntryOutcome::Sent {
+                message_id,
+                sequence_number,
+            } => println!(
+                "[{tag}] entry={} sent message_id={} sequence={}",
+                i,
+                message_id,
+                sequence_number.as_deref().unwrap_or("-")
+            ),
+            EntryOutcome::Failed {
+                code,
+                message,
+                sender_fault,
+            } => {
+                failed += 1;
+                println!(
+                    "[{tag}] entry={} failed code={} sender_fault={} message={:?}",
+                    i, code, sender_fault, message
+                );
+            }
+        }
+    }
+    println!(
+        "[{tag}] batch summary: sent={} failed={}",
+        outcomes.len() - failed,
+        failed
+    );
+    failed
+}
+
+/// A receipt handle DeleteMessageBatch could not delete.
+#[derive(Debug, Clone)]
+pub struct DeleteFailure {
+    /// Position in the input slice
+    pub index: usize,
+    pub receipt_handle: String,
+    pub code: String,
+    pub message: String,
+}
+
+/// Delete messages with DeleteMessageBatch (10 per call) and return the entries that failed.
+pub async fn delete_all(
+    client: &Client,
+    queue_url: &str,
+    receipt_handles: &[String],
+) -> Result<Vec<DeleteFailure>> {
+    let mut failures = Vec::new();
+
+    for (chunk_no, chunk) in receipt_handles.chunks(MAX_BATCH_ENTRIES).enumerate() {
+        let base = chunk_no * MAX_BATCH_ENTRIES;
+        let mut req = client.delete_message_batch().queue_url(queue_url);
+        for (offset, rh) in chunk.iter().enumerate() {
+            let entry = DeleteMessageBatchRequestEntry::builder()
+                .id((base + offset).to_string())
+                .receipt_handle(rh)
+                .build()?;
+            req = req.entries(entry);
+        }
+
+        let out = req.send().await.context("delete_message_batch")?;
+        for failed in out.failed() {
+            let index: usize = failed.id().parse().context("unexpected batch entry id")?;
+            failures.push(DeleteFailure {
+                index,
+                receipt_handle: receipt_handles[index].clone(),
+                code: failed.code().to_string(),
+                message: failed.message().unwrap_or_default().to_string(),
+            });
+        }
+    }
+    Ok(failures)
+}
@@ -1,8 +1,12 @@
 use anyhow::Result;
+use aws_sdk_sqs::Client;
 use clap::Parser;
-use shared::cli::{CommonArgs, merged_config, require_queue_name};
-use shared::config::build_sqs_client;
-use shared::{logging, sqs};
+use shared::cli::{CommonArgs, merged_config, require_queue_name, topic_name};
+use shared::config::{SnsConfig, SqsConfig, build_sqs_client};
+use shared::offload::Offloader;
+use shared::reconcile;
+use shared::sns::build_sns_client;
+use shared::{logging, sns, sqs};
 use tracing::{info, warn};
 
 #[derive(Parser, Debug)]
@@ -10,6 +14,50 @@ use tracing::{info, warn};
 struct Args {
     #[command(flatten)]
     common: CommonArgs,
+
+    /// Fix attribute drift on existing queues with SetQueueAttributes
+    /// (without it the differences are only printed)
+    #[arg(long)]
+    apply: bool,
+}
+
+/// Return the URL of `name`, creating it from `sqs_cfg` if it does not exist yet.
+/// For an existing queue, print how its attributes differ from `sqs_cfg` and
+/// apply the mutable differences when `apply` is set.
+async fn ensure_queue(
+    client: &Client,
+    name: &str,
+    sqs_cfg: &SqsConfig,
+    apply: bool,
+) -> Result<String> {
+    match sqs::get_queue_url(client, name).await {
+        Ok(u) => {
+            info!("Queue already exists: {u}");
+            let plan = reconcile::plan(client, &u, name, sqs_cfg).await?;
+            plan.print();
+            if plan.needs_recreate() {
+                warn!(
+                    "{} differs in attributes that cannot change; delete it (teardown) and bootstrap again",
+                    name
+                );
+            }
+            if apply {
+                let n = plan.apply(client, &u).await?;
+                if n > 0 {
+                    println!("[apply] {}: set {} attribute(s)", name, n);
+                }
+            } else if plan.changes.iter().any(|c| !c.requires_recreate()) {
+                println!("[plan] {}: run with --apply to update", name);
+            }
+            Ok(u)
+        }
+        Err(_) => {
+            warn!("Queue not found, creating: {}", name);
+            let u = sqs::create_queue(client, name, sqs_cfg).await?;
+            info!("Created queue: {u}");
+            Ok(u)
+        }
+    }
 }
 
 #[tokio::main]
@@ -23,19 +71,85 @@ async fn main() -> Result<()> {
 
     let qname = require_queue_name(&args.common, &cfg)?;
 
-    let url = match sqs::get_queue_url(&sqs_client, &qname).await {
-        Ok(u) => {
-            info!("Queue already exists: {u}");
-            u
+    // Reject invalid queue settings before the first AWS call
+    sqs::queue_attributes(&qname, &cfg.sqs)?;
+    if let Some(dlq_cfg) = sqs::dlq_config(&cfg.sqs, &qname) {
+        let dlq_name = dlq_cfg.queue_name.as_deref().unwrap_or_default();
+        sqs::check_dlq_type(&qname, dlq_name)?;
+        sqs::queue_attributes(dlq_name, &dlq_cfg)?;
+        sqs::check_max_receive_count(cfg.sqs.dlq.as_ref().and_then(|d| d.max_receive_count))?;
+    }
+    let sns_cfg = topic_name(&args.common, &cfg).map(|t| SnsConfig {
+        topic_name: Some(t),
+        ..cfg.sns.clone()
+    });
+    if let Some(sns_cfg) = &sns_cfg {
+        // FIFO-ness follows the names, so a mismatch is known before the topic exists
+        sns::topic_attributes(sns_cfg)?;
+        let topic = sns_cfg.topic_name.as_deref().unwrap_or_default();
+        for sub in &cfg.sns.subscriptions {
+            sns::validate_subscription(topic, &sub.queue_name)?;
+            let sub_cfg = sqs::subscriber_config(&cfg.sqs, &sub.queue_name);
+            sqs::queue_attributes(&sub.queue_name, &sub_cfg)?;
         }
-        Err(_) => {
-            warn!("Queue not found, creating: {}", qname);
-            let u = sqs::create_queue(&sqs_client, &cfg.sqs).await?;
-            info!("Created queue: {u}");
-            u
+    }
+
+    // DLQ first, so its ARN is available for the main queue's RedrivePolicy
+    let dlq = match sqs::dlq_config(&cfg.sqs, &qname) {
+        Some(dlq_cfg) => {
+            let dlq_name = dlq_cfg.queue_name.clone().unwrap_or_default();
+            let dlq_url = ensure_queue(&sqs_client, &dlq_name, &dlq_cfg, args.apply).await?;
+            let dlq_arn = sqs::get_queue_arn(&sqs_client, &dlq_url).await?;
+            Some((dlq_url, dlq_arn))
         }
+        None => None,
     };
 
+    let url = ensure_queue(&sqs_client, &qname, &cfg.sqs, args.apply).await?;
+
+    if let Some((dlq_url, dlq_arn)) = dlq {
+        let max_receive_count = cfg.sqs.dlq.as_ref().and_then(|d| d.max_receive_count);
+        sqs::set_redrive_policy(&sqs_client, &url, &dlq_arn, max_receive_count).await?;
+        let arn = sqs::get_queue_arn(&sqs_client, &url).await?;
+        sqs::set_redrive_allow_policy(&sqs_client, &dlq_url, &arn).await?;
+        info!("Redrive configured: {} -> {}", qname, dlq_arn);
+    }
+
+    if let Some(sns_cfg) = &sns_cfg {
+        let sns_client = build_sns_client(&cfg).await?;
+        let topic_arn = sns::create_topic(&sns_client, sns_cfg).await?;
+        info!("Topic ready: {topic_arn}");
+
+        for sub in &cfg.sns.subscriptions {
+            // The main queue may subscribe too; it was created and planned above
+            let sub_url = if sub.queue_name == qname {
+                url.clone()
+            } else {
+                let sub_cfg = sqs::subscriber_config(&cfg.sqs, &sub.queue_name);
+                ensure_queue(&sqs_client, &sub.queue_name, &sub_cfg, args.apply).await?
+            };
+            let queue_arn = sqs::get_queue_arn(&sqs_client, &sub_url).await?;
+
+            // Without this policy SNS deliveries to the queue are silently dropped
+            sqs::allow_sns_send(&sqs_client, &sub_url, &queue_arn, &topic_arn).await?;
+
+            let subscription_arn =
+                sns::subscribe_queue(&sns_client, &topic_arn, &queue_arn, sub).await?;
+            info!(
+                "Subscribed {} (raw={} filter={}): {}",
+                sub.queue_name,
+                sub.raw_message_delivery.unwrap_or(false),
+                sub.filter_policy.is_some(),
+                subscription_arn
+            );
+        }
+    }
+
+    // Bucket for large payloads (extended client mode)
+    if let Some(off) = Offloader::from_config(&cfg).await? {
+        off.ensure_bucket().await?;
+    }
+
     sqs::print_attrs(&sqs_client, &url).await.ok();
     Ok(())
 }
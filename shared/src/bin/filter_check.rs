@@ -0,0 +1,97 @@
+use std::collections::BTreeMap;
+
+use anyhow::{Context, Result, anyhow};
+use clap::Parser;
+use serde_json::Value;
+use shared::cli::{CommonArgs, merged_config};
+use shared::config::FilterPolicyScope;
+use shared::{filter_policy, logging};
+
+/// Evaluate an SNS filter policy locally (no LocalStack needed).
+/// Exits with status 1 when the message does not match.
+#[derive(Parser, Debug)]
+#[command(name = "filter_check")]
+struct Args {
+    #[command(flatten)]
+    common: CommonArgs,
+
+    /// Use the filter policy of the configured subscription for this queue
+    #[arg(long, value_name = "QUEUE", conflicts_with = "policy")]
+    sub: Option<String>,
+
+    /// Filter policy as inline JSON or @path/to/policy.json
+    #[arg(long)]
+    policy: Option<String>,
+
+    /// What the policy is matched against (defaults to the subscription's scope)
+    #[arg(long, value_enum)]
+    scope: Option<FilterPolicyScope>,
+
+    /// Sample message body
+    #[arg(long, default_value = "")]
+    msg: String,
+
+    /// Sample attribute as key=value or key:Type=value (Type: String, Number, String.Array)
+    #[arg(long = "attr")]
+    attrs: Vec<String>,
+}
+
+fn parse_attr(kv: &str) -> Result<(String, Vec<Value>)> {
+    let (k, v) = kv
+        .split_once('=')
+        .ok_or_else(|| anyhow!("Invalid --attr '{}'. Use key=value or key:Type=value.", kv))?;
+    let (k, dt) = k.split_once(':').unwrap_or((k, "String"));
+    if k.is_empty() {
+        return Err(anyhow!("Attribute key cannot be empty"));
+    }
+    Ok((k.to_string(), filter_policy::attribute_values(dt, v)?))
+}
+
+fn load_policy(arg: &str) -> Result<Value> {
+    let raw = match arg.strip_prefix('@') {
+        Some(path) => std::fs::read_to_string(path).with_context(|| format!("reading {path}"))?,
+        None => arg.to_string(),
+    };
+    serde_json::from_str(&raw).context("parsing filter policy JSON")
+}
+
+fn main() -> Result<()> {
+    logging::init();
+    let args = Args::parse();
+
+    let (policy, cfg_scope) = match (&args.sub, &args.policy) {
+        (Some(queue), _) => {
+            let default_lab_cfg = format!("{}/config.toml", env!("CARGO_MANIFEST_DIR"));
+            let cfg = merged_config(&args.common, &default_lab_cfg)?;
+            let sub = cfg
+                .sns
+                .subscriptions
+                .iter()
+                .find(|s| &s.queue_name == queue)
+                .ok_or_else(|| anyhow!("No [[sns.subscriptions]] entry for queue {}", queue))?;
+            let policy = sub
+                .filter_policy
+                .clone()
+                .ok_or_else(|| anyhow!("Subscription for {} has no filter_policy", queue))?;
+            (policy, sub.filter_policy_scope)
+        }
+        (None, Some(p)) => (load_policy(p)?, None),
+        (None, None) => return Err(anyhow!("Pass --policy <json|@file> or --sub <queue_name>")),
+    };
+    let scope = args.scope.or(cfg_scope).unwrap_or_default();
+
+    let attrs = args
+        .attrs
+        .iter()
+        .map(|kv| parse_attr(kv))
+        .collect::<Result<BTreeMap<_, _>>>()?;
+
+    println!("[filter_check] scope={} policy={}", scope.as_str(), policy);
+    if filter_policy::matches(&policy, scope, &attrs, &args.msg)? {
+        println!("[filter_check] MATCH");
+        Ok(())
+    } else {
+        println!("[filter_check] NO MATCH");
+        std::process::exit(1);
+    }
+}
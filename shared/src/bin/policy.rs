@@ -0,0 +1,45 @@
+use anyhow::Result;
+use clap::{Parser, Subcommand};
+use shared::cli::{CommonArgs, merged_config, require_queue_name};
+use shared::config::build_sqs_client;
+use shared::{logging, sqs};
+
+#[derive(Parser, Debug)]
+#[command(name = "policy")]
+struct Args {
+    #[command(flatten)]
+    common: CommonArgs,
+
+    #[command(subcommand)]
+    cmd: Cmd,
+}
+
+#[derive(Subcommand, Debug)]
+enum Cmd {
+    /// Pretty-print the effective queue access policy
+    Show,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    logging::init();
+    let args = Args::parse();
+
+    let default_lab_cfg = format!("{}/config.toml", env!("CARGO_MANIFEST_DIR"));
+    let cfg = merged_config(&args.common, &default_lab_cfg)?;
+    let client = build_sqs_client(&cfg).await?;
+
+    let qname = require_queue_name(&args.common, &cfg)?;
+    let url = sqs::get_queue_url(&client, &qname).await?;
+
+    match args.cmd {
+        Cmd::Show => match sqs::get_queue_policy(&client, &url).await? {
+            Some(policy) => {
+                println!("[policy] queue={}", qname);
+                println!("{}", serde_json::to_string_pretty(&policy)?);
+            }
+            None => println!("[policy] queue={} has no policy", qname),
+        },
+    }
+    Ok(())
+}
@@ -1,9 +1,14 @@
-use anyhow::Result;
+use std::time::Duration;
+
+use anyhow::{Result, bail};
+use aws_sdk_sqs::types::Message;
 use clap::Parser;
-use shared::cli::{CommonArgs, merged_config, require_queue_name};
+use shared::cli::{CommonArgs, ConsumerArgs, merged_config, require_queue_name};
 use shared::config::build_sqs_client;
-use shared::{logging, sqs};
-use tracing::warn;
+use shared::consumer::{Ack, Consumer, ConsumerOptions, Handler};
+use shared::encryption::Envelope;
+use shared::offload::Offloader;
+use shared::{logging, sns, sqs};
 
 #[derive(Parser, Debug)]
 #[command(name = "recv")]
@@ -11,9 +16,65 @@ struct Args {
     #[command(flatten)]
     common: CommonArgs,
 
-    /// Do not delete messages after receiving (observe redelivery)
+    #[command(flatten)]
+    consumer: ConsumerArgs,
+
+    /// Print SNS-delivered bodies as-is instead of unwrapping the envelope
     #[arg(long)]
-    no_delete: bool,
+    raw: bool,
+
+    /// Pretend each message takes this long to handle (compare with the visibility timeout)
+    #[arg(long, value_name = "SECS")]
+    simulate_work: Option<u64>,
+
+    /// Treat every message as a handler failure (backs off per [recv.retry] if configured)
+    #[arg(long)]
+    simulate_failure: bool,
+}
+
+/// Prints each message (unwrapping SNS envelopes) and optionally simulates work/failures.
+struct PrintHandler {
+    raw: bool,
+    simulate_work: Option<u64>,
+    simulate_failure: bool,
+    heartbeat: bool,
+}
+
+impl Handler for PrintHandler {
+    async fn handle(&self, m: &Message) -> Result<Ack> {
+        let mid = m.message_id().unwrap_or("unknown");
+        let body = m.body().unwrap_or("");
+
+        match sns::parse_envelope(body).filter(|_| !self.raw) {
+            Some(env) => {
+                println!("[recv] received: message_id={} body={:?}", mid, env.message);
+                println!("[recv] sns: TopicArn={}", env.topic_arn);
+                if let Some(subject) = &env.subject {
+                    println!("[recv] sns: Subject={}", subject);
+                }
+                println!("[recv] sns: Timestamp={}", env.timestamp);
+                for (k, v) in &env.message_attributes {
+                    println!("[recv] attrs: {}({})={:?}", k, v.data_type, v.value);
+                }
+            }
+            None => println!("[recv] received: message_id={} body={:?}", mid, body),
+        }
+
+        if let Some(secs) = self.simulate_work {
+            println!(
+                "[recv] working {}s on message_id={} (heartbeat {})",
+                secs,
+                mid,
+                if self.heartbeat { "on" } else { "off" }
+            );
+            tokio::time::sleep(Duration::from_secs(secs)).await;
+        }
+
+        if self.simulate_failure {
+            bail!("simulated failure");
+        }
+        Ok(Ack::Delete)
+    }
 }
 
 #[tokio::main]
@@ -28,50 +89,31 @@ async fn main() -> Result<()> {
     let qname = require_queue_name(&args.common, &cfg)?;
     let url = sqs::get_queue_url(&client, &qname).await?;
 
-    let wait_secs = cfg.recv_wait_secs();
+    let opts = ConsumerOptions::from_args("recv", &cfg, &args.consumer)?;
 
     println!(
-        "[recv] region={} queue={} mode={:?} wait={}s delete={}",
-        cfg.runtime.region, qname, cfg.runtime.mode, wait_secs, !args.no_delete
+        "[recv] region={} queue={} mode={:?} wait={}s delete={} batch={}",
+        cfg.runtime.region,
+        qname,
+        cfg.runtime.mode,
+        opts.wait_secs,
+        !opts.no_delete,
+        opts.batch_size
     );
-    println!("[recv] waiting for messages... (Ctrl+C to stop)");
-
-    loop {
-        let out = client
-            .receive_message()
-            .queue_url(&url)
-            .max_number_of_messages(1)
-            .wait_time_seconds(wait_secs)
-            .send()
-            .await?;
-
-        let msgs = out.messages();
-        if msgs.is_empty() {
-            continue;
-        }
 
-        for m in msgs {
-            let mid = m.message_id().unwrap_or("unknown");
-            let body = m.body().unwrap_or("");
-            println!("[recv] received: message_id={} body={:?}", mid, body);
-
-            if args.no_delete {
-                warn!("--no-delete set; not deleting message_id={}", mid);
-                continue;
-            }
+    let handler = PrintHandler {
+        raw: args.raw,
+        simulate_work: args.simulate_work,
+        simulate_failure: args.simulate_failure,
+        heartbeat: args.consumer.heartbeat,
+    };
+    let offload = Offloader::from_config(&cfg).await?;
+    let encryption = Envelope::from_config(&cfg).await?;
+    let consumer = Consumer::new(client, url, handler, opts)
+        .with_offload(offload)
+        .with_encryption(encryption);
+    consumer.print_settings();
+    println!("[recv] waiting for messages... (Ctrl+C to stop)");
 
-            if let Some(rh) = m.receipt_handle() {
-                println!("[recv] deleting...");
-                client
-                    .delete_message()
-                    .queue_url(&url)
-                    .receipt_handle(rh)
-                    .send()
-                    .await?;
-                println!("[recv] deleted message_id={}", mid);
-            } else {
-                warn!("missing receipt_handle; cannot delete");
-            }
-        }
-    }
+    consumer.run().await
 }
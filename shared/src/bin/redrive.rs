@@ -0,0 +1,224 @@
+use std::collections::HashSet;
+
+use anyhow::{Result, anyhow};
+use aws_sdk_sqs::types::{Message, MessageSystemAttributeName};
+use clap::Parser;
+use shared::cli::{CommonArgs, merged_config, require_queue_name};
+use shared::config::build_sqs_client;
+use shared::{logging, sqs};
+use tracing::warn;
+
+#[derive(Parser, Debug)]
+#[command(name = "redrive")]
+struct Args {
+    #[command(flatten)]
+    common: CommonArgs,
+
+    /// Queue to drain (defaults to the configured DLQ of the main queue)
+    #[arg(long, value_name = "QUEUE")]
+    from: Option<String>,
+
+    /// Queue to move messages into (defaults to the main queue)
+    #[arg(long, value_name = "QUEUE")]
+    to: Option<String>,
+
+    /// Stop after moving this many messages
+    #[arg(long)]
+    max: Option<usize>,
+
+    /// Only move messages whose attribute matches key=value (repeatable, all must match)
+    #[arg(long = "filter-attr", value_name = "KEY=VALUE")]
+    filter_attrs: Vec<String>,
+
+    /// Print what would be moved without sending or deleting anything
+    #[arg(long)]
+    dry_run: bool,
+}
+
+fn parse_filter(kv: &str) -> Result<(String, String)> {
+    let (k, v) = kv
+        .split_once('=')
+        .ok_or_else(|| anyhow!("Invalid --filter-attr '{}'. Use key=value.", kv))?;
+    if k.is_empty() {
+        return Err(anyhow!("Filter attribute key cannot be empty"));
+    }
+    Ok((k.to_string(), v.to_string()))
+}
+
+fn matches_filters(m: &Message, filters: &[(String, String)]) -> bool {
+    filters.iter().all(|(k, v)| {
+        m.message_attributes()
+            .and_then(|a| a.get(k))
+            .and_then(|val| val.string_value())
+            == Some(v.as_str())
+    })
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    logging::init();
+    let args = Args::parse();
+
+    let default_lab_cfg = format!("{}/config.toml", env!("CARGO_MANIFEST_DIR"));
+    let cfg = merged_config(&args.common, &default_lab_cfg)?;
+    let client = build_sqs_client(&cfg).await?;
+
+    let qname = require_queue_name(&args.common, &cfg)?;
+    let from = match args.from.clone() {
+        Some(f) => f,
+        None => sqs::dlq_config(&cfg.sqs, &qname)
+            .and_then(|d| d.queue_name)
+            .ok_or_else(|| {
+                anyhow!("No DLQ configured. Pass --from or set [sqs.dlq] in the lab config.")
+            })?,
+    };
+    let to = args.to.clone().unwrap_or(qname);
+
+    let filters = args
+        .filter_attrs
+        .iter()
+        .map(|kv| parse_filter(kv))
+        .collect::<Result<Vec<_>>>()?;
+
+    let from_url = sqs::get_queue_url(&client, &from).await?;
+    let to_url = sqs::get_queue_url(&client, &to).await?;
+    let to_fifo = to.ends_with(".fifo");
+
+    println!(
+        "[redrive] from={} to={} max={} filters={} dry_run={}",
+        from,
+        to,
+        args.max
+            .map_or_else(|| "all".to_string(), |m| m.to_string()),
+        filters.len(),
+        args.dry_run
+    );
+
+    let (mut moved, mut skipped, mut failed) = (0usize, 0usize, 0usize);
+    // Skipped/dry-run messages stay in the source queue; remember them so we stop
+    // once only already-seen messages come back.
+    let mut seen = HashSet::new();
+    // Receipt handles of messages left in the source queue. They are kept invisible
+    // while scanning (so every receive brings new ones) and released at the end,
+    // so a rerun with other filters sees them without waiting out the timeout.
+    let mut left = Vec::new();
+
+    'outer: loop {
+        let out = client
+            .receive_message()
+            .queue_url(&from_url)
+            .max_number_of_messages(10)
+            .wait_time_seconds(1)
+            .message_attribute_names("All")
+            .message_system_attribute_names(MessageSystemAttributeName::MessageGroupId)
+            .send()
+            .await?;
+
+        let msgs = out.messages();
+        let mut fresh = 0;
+
+        for (i, m) in msgs.iter().enumerate() {
+            if args.max.is_some_and(|max| moved >= max) {
+                left.extend(
+                    msgs[i..]
+                        .iter()
+                        .filter_map(|m| m.receipt_handle().map(str::to_string)),
+                );
+                break 'outer;
+            }
+
+            let mid = m.message_id().unwrap_or("unknown").to_string();
+            if !seen.insert(mid.clone()) {
+                left.extend(m.receipt_handle().map(str::to_string));
+                continue;
+            }
+            fresh += 1;
+
+            if !matches_filters(m, &filters) {
+                println!("[redrive] skipped message_id={} (filter mismatch)", mid);
+                skipped += 1;
+                left.extend(m.receipt_handle().map(str::to_string));
+                continue;
+            }
+
+            if args.dry_run {
+                println!(
+                    "[redrive] would move message_id={} body={:?}",
+                    mid,
+                    m.body().unwrap_or("")
+                );
+                moved += 1;
+                left.extend(m.receipt_handle().map(str::to_string));
+                continue;
+            }
+
+            let mut req = client
+                .send_message()
+                .queue_url(&to_url)
+                .message_body(m.body().unwrap_or(""))
+                .set_message_attributes(m.message_attributes().cloned());
+
+            if to_fifo {
+                let group = m
+                    .attributes()
+                    .and_then(|a| a.get(&MessageSystemAttributeName::MessageGroupId))
+                    .cloned()
+                    .unwrap_or_else(|| "redrive".to_string());
+                // The original message id keeps retried redrives idempotent
+                req = req.message_group_id(group).message_deduplication_id(&mid);
+            }
+
+            if let Err(e) = req.send().await {
+                warn!("send failed for message_id={}: {}", mid, e);
+                failed += 1;
+                left.extend(m.receipt_handle().map(str::to_string));
+                continue;
+            }
+
+            let Some(rh) = m.receipt_handle() else {
+                warn!(
+                    "missing receipt_handle; message_id={} was copied but not deleted",
+                    mid
+                );
+                failed += 1;
+                continue;
+            };
+            if let Err(e) = client
+                .delete_message()
+                .queue_url(&from_url)
+                .receipt_handle(rh)
+                .send()
+                .await
+            {
+                warn!(
+                    "delete failed for message_id={}: {}; it was already sent to {}, so it is now in both queues",
+                    mid, e, to
+                );
+                failed += 1;
+                continue;
+            }
+
+            println!("[redrive] moved message_id={}", mid);
+            moved += 1;
+        }
+
+        if fresh == 0 {
+            break;
+        }
+    }
+
+    for rh in left {
+        if let Err(e) = sqs::change_visibility(&client, &from_url, &rh, 0).await {
+            warn!("failed to release a message left in the source queue: {e:#}");
+        }
+    }
+
+    println!(
+        "[redrive] summary: {}={} skipped={} failed={}",
+        if args.dry_run { "would_move" } else { "moved" },
+        moved,
+        skipped,
+        failed
+    );
+    Ok(())
+}
@@ -1,7 +1,12 @@
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use clap::Parser;
+use shared::batch::{self, BatchEntry};
 use shared::cli::{CommonArgs, merged_config, require_queue_name};
+use shared::codec::{self, Compression, SizeReport};
 use shared::config::build_sqs_client;
+use shared::encryption::Envelope;
+use shared::offload::Offloader;
+use shared::validate::{self, QueueKind};
 use shared::{logging, sqs};
 use tracing::info;
 
@@ -17,6 +22,56 @@ struct Args {
 
     /// Positional message fallback
     message: Option<String>,
+
+    /// Send N messages via SendMessageBatch (bodies get a " #i" suffix)
+    #[arg(long, value_name = "N", conflicts_with = "from_file")]
+    count: Option<usize>,
+
+    /// Send every line of a JSONL file via SendMessageBatch
+    /// (lines: {"body": "...", "attrs": {...}, "group": "...", "dedup": "..."})
+    #[arg(long, value_name = "PATH")]
+    from_file: Option<String>,
+
+    /// Compress bodies (base64-wrapped, tagged with a content-encoding attribute)
+    #[arg(long, value_enum, value_name = "CODEC")]
+    compress: Option<Compression>,
+}
+
+fn print_report(label: &str, codec: &str, r: &SizeReport) {
+    if r.skipped {
+        println!(
+            "[send] {} {}: {} bytes, not smaller when compressed, sent as-is",
+            label, codec, r.original
+        );
+        return;
+    }
+    let (before, after) = r.chunks();
+    println!(
+        "[send] {} {}: {} -> {} bytes ({:.1}% saved), 64 KiB chunks {} -> {}",
+        label,
+        codec,
+        r.original,
+        r.encoded,
+        r.saved_pct(),
+        before,
+        after
+    );
+}
+
+/// Compress every entry, printing per-message and total savings.
+fn compress_all(entries: &mut [BatchEntry], compression: Compression) -> Result<()> {
+    let codec = compression.codec();
+    let mut total = SizeReport::default();
+    for (i, e) in entries.iter_mut().enumerate() {
+        let (encoded, report) = codec::encode_entry(std::mem::take(e), codec)?;
+        *e = encoded;
+        print_report(&format!("#{i}"), codec.name(), &report);
+        total.add(report);
+    }
+    if entries.len() > 1 {
+        print_report("total", codec.name(), &total);
+    }
+    Ok(())
 }
 
 #[tokio::main]
@@ -29,17 +84,91 @@ async fn main() -> Result<()> {
     let client = build_sqs_client(&cfg).await?;
 
     let qname = require_queue_name(&args.common, &cfg)?;
-    let url = sqs::get_queue_url(&client, &qname).await?;
+    let kind = QueueKind::from_name(&qname);
 
     let body = args
         .msg
         .or(args.message)
         .unwrap_or_else(|| "hello world".into());
 
+    // Batch mode
+    let entries: Option<Vec<BatchEntry>> = match (args.count, &args.from_file) {
+        (Some(0), _) => return Err(anyhow!("--count must be at least 1")),
+        (Some(n), _) => Some(
+            (0..n)
+                .map(|i| BatchEntry {
+                    body: format!("{body} #{i}"),
+                    ..Default::default()
+                })
+                .collect(),
+        ),
+        (None, Some(path)) => Some(
+            batch::load_jsonl(path)?
+                .into_iter()
+                .map(|e| e.into_entry())
+                .collect::<Result<_>>()?,
+        ),
+        (None, None) => None,
+    };
+    // Validate locally before any network call; with [offload] the size limit
+    // no longer applies because oversized bodies go to S3, and with --compress
+    // or [encryption] it is checked on the encoded entries. Offloading adds an
+    // attribute, so the final check runs on what is actually sent.
+    let offloader = Offloader::from_config(&cfg).await?;
+    let envelope = Envelope::from_config(&cfg).await?;
+    let batch_mode = entries.is_some();
+    let mut entries = entries.unwrap_or_else(|| {
+        vec![BatchEntry {
+            body,
+            ..Default::default()
+        }]
+    });
+    let encoded = args.compress.is_some() || envelope.is_some();
+    let first_limit = if encoded || offloader.is_some() {
+        usize::MAX
+    } else {
+        validate::MAX_MESSAGE_BYTES
+    };
+    validate::check_entries(&entries, kind, first_limit)?;
+    if let Some(compression) = args.compress {
+        compress_all(&mut entries, compression)?;
+    }
+    if let Some(env) = &envelope {
+        for e in entries.iter_mut() {
+            *e = env.seal(std::mem::take(e)).await?;
+        }
+        println!(
+            "[send] encrypted {} message(s) ({:?}, key {})",
+            entries.len(),
+            env.mode(),
+            env.key_id()
+        );
+    }
+    if let Some(off) = &offloader {
+        for e in entries.iter_mut() {
+            *e = off.offload(std::mem::take(e)).await?;
+        }
+    }
+    if first_limit != validate::MAX_MESSAGE_BYTES {
+        validate::check_entries(&entries, kind, validate::MAX_MESSAGE_BYTES)?;
+    }
+
+    let url = sqs::get_queue_url(&client, &qname).await?;
+    if batch_mode {
+        let outcomes = batch::send_all(&client, &url, &entries).await?;
+        let failed = batch::print_outcomes("send", &outcomes);
+        if failed > 0 {
+            return Err(anyhow!("{} of {} messages failed", failed, entries.len()));
+        }
+        return Ok(());
+    }
+
+    let single = entries.pop().expect("single message");
     let out = client
         .send_message()
         .queue_url(&url)
-        .message_body(body)
+        .message_body(single.body)
+        .set_message_attributes((!single.attributes.is_empty()).then_some(single.attributes))
         .send()
         .await?;
 
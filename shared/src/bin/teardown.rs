@@ -1,9 +1,11 @@
 use anyhow::{Context, Result};
 use clap::Parser;
-use shared::cli::{CommonArgs, merged_config, require_queue_name};
+use shared::cli::{CommonArgs, merged_config, require_queue_name, topic_name};
 use shared::config::build_sqs_client;
-use shared::{logging, sqs};
-use tracing::info;
+use shared::offload::Offloader;
+use shared::sns::build_sns_client;
+use shared::{logging, sns, sqs};
+use tracing::{info, warn};
 
 #[derive(Parser, Debug)]
 #[command(name = "teardown")]
@@ -28,5 +30,52 @@ async fn main() -> Result<()> {
 
     sqs::delete_queue(&client, &url).await?;
     info!("Deleted queue: {}", url);
+
+    // Remove the DLQ after its source queue
+    if let Some(dlq_name) = sqs::dlq_config(&cfg.sqs, &qname).and_then(|d| d.queue_name) {
+        match sqs::get_queue_url(&client, &dlq_name).await {
+            Ok(dlq_url) => {
+                sqs::delete_queue(&client, &dlq_url).await?;
+                info!("Deleted DLQ: {}", dlq_url);
+            }
+            Err(_) => warn!("DLQ {} not found; skipping", dlq_name),
+        }
+    }
+
+    // Deleting the topic also removes its subscriptions
+    if let Some(topic_name) = topic_name(&args.common, &cfg) {
+        let sns_client = build_sns_client(&cfg).await?;
+        match sns::get_topic_arn(&sns_client, &topic_name).await {
+            Ok(arn) => {
+                sns::delete_topic(&sns_client, &arn).await?;
+                info!("Deleted topic: {}", arn);
+            }
+            Err(_) => warn!("Topic {} not found; skipping", topic_name),
+        }
+    }
+
+    // Subscriber queues created by bootstrap (the main queue is already gone)
+    for sub in cfg
+        .sns
+        .subscriptions
+        .iter()
+        .filter(|s| s.queue_name != qname)
+    {
+        match sqs::get_queue_url(&client, &sub.queue_name).await {
+            Ok(sub_url) => {
+                sqs::delete_queue(&client, &sub_url).await?;
+                info!("Deleted subscriber queue: {}", sub_url);
+            }
+            Err(_) => warn!("Subscriber queue {} not found; skipping", sub.queue_name),
+        }
+    }
+
+    // Offload bucket, including payloads nobody consumed
+    if let Some(off) = Offloader::from_config(&cfg).await? {
+        match off.delete_bucket().await {
+            Ok(()) => info!("Deleted bucket: {}", off.bucket),
+            Err(e) => warn!("{e:#}"),
+        }
+    }
     Ok(())
 }
@@ -18,6 +18,56 @@ pub struct CommonArgs {
     /// Ad-hoc override for the queue name
     #[arg(long)]
     pub queue_name: Option<String>,
+
+    /// Ad-hoc override for the SNS topic name
+    #[arg(long)]
+    pub topic_name: Option<String>,
+}
+
+/// Consumer flags shared by the recv-style binaries.
+/// Use with `#[command(flatten)] consumer: ConsumerArgs`.
+#[derive(Clone, Debug, ClapArgs)]
+pub struct ConsumerArgs {
+    /// Do not delete messages after receiving (observe redelivery)
+    #[arg(long)]
+    pub no_delete: bool,
+
+    /// Messages per ReceiveMessage call (acks use DeleteMessageBatch)
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(i32).range(1..=10))]
+    pub batch_size: i32,
+
+    /// Extend visibility of in-flight messages while handling (see [recv.heartbeat])
+    #[arg(long)]
+    pub heartbeat: bool,
+
+    /// Concurrent pollers feeding a shared worker pool (pool mode when > 1)
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..))]
+    pub workers: u16,
+
+    /// Max messages handled at once; polling pauses while the pool is full
+    /// (enables pool mode; defaults to workers * batch-size)
+    #[arg(long, value_name = "M", value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_in_flight: Option<u32>,
+
+    /// FIFO: dispatch to one serial lane per MessageGroupId (groups run concurrently)
+    #[arg(long)]
+    pub per_group: bool,
+
+    /// FIFO: flag any SequenceNumber that goes backwards within a group
+    #[arg(long)]
+    pub verify_order: bool,
+
+    /// Stop polling after receiving N messages, finish them and exit
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    pub max_messages: Option<u64>,
+
+    /// Stop polling once no message arrived for this long, finish in-flight ones and exit
+    #[arg(long, value_name = "SECS")]
+    pub idle_exit: Option<u64>,
+
+    /// Seconds between throughput/in-flight reports in pool mode
+    #[arg(long, value_name = "SECS", default_value_t = 5)]
+    pub report_every: u64,
 }
 
 /// Merge root + lab + env into an AppConfig.
@@ -42,3 +92,20 @@ pub fn require_queue_name(common: &CommonArgs, cfg: &AppConfig) -> Result<String
             )
         })
 }
+
+/// Topic name from either CLI or config, if any.
+pub fn topic_name(common: &CommonArgs, cfg: &AppConfig) -> Option<String> {
+    common
+        .topic_name
+        .clone()
+        .or_else(|| cfg.sns.topic_name.clone())
+}
+
+/// Require a topic name from either CLI or config; otherwise fail.
+pub fn require_topic_name(common: &CommonArgs, cfg: &AppConfig) -> Result<String> {
+    topic_name(common, cfg).ok_or_else(|| {
+        anyhow!(
+            "Topic name is required. Pass --topic-name or set [sns].topic_name in the lab config."
+        )
+    })
+}
@@ -0,0 +1,256 @@
+//! Body compression codecs. Compressed bodies are base64-wrapped (SQS bodies
+//! must be text) and tagged with a `content-encoding` attribute; the consumer
+//! decodes them before the handler runs.
+
+use std::io::{Read, Write};
+
+use anyhow::{Context, Result, anyhow};
+use aws_sdk_sqs::types::{Message, MessageAttributeValue};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+
+use crate::batch::BatchEntry;
+
+pub const CONTENT_ENCODING_ATTR: &str = "content-encoding";
+
+/// SQS bills requests per started 64 KiB of payload.
+pub const BILLING_CHUNK_BYTES: usize = 64 * 1024;
+
+/// A compression algorithm; its `name` is the `content-encoding` value.
+pub trait Codec: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn compress(&self, data: &[u8]) -> Result<Vec<u8>>;
+    fn decompress(&self, data: &[u8]) -> Result<Vec<u8>>;
+}
+
+pub struct Gzip;
+
+impl Codec for Gzip {
+    fn name(&self) -> &'static str {
+        "gzip"
+    }
+
+    fn compress(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let mut enc = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        enc.write_all(data)?;
+        Ok(enc.finish()?)
+    }
+
+    fn decompress(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let mut out = Vec::new();
+        flate2::read::GzDecoder::new(data).read_to_end(&mut out)?;
+        Ok(out)
+    }
+}
+
+pub struct Zstd;
+
+impl Codec for Zstd {
+    fn name(&self) -> &'static str {
+        "zstd"
+    }
+
+    fn compress(&self, data: &[u8]) -> Result<Vec<u8>> {
+        Ok(zstd::encode_all(data, zstd::DEFAULT_COMPRESSION_LEVEL)?)
+    }
+
+    fn decompress(&self, data: &[u8]) -> Result<Vec<u8>> {
+        Ok(zstd::decode_all(data)?)
+    }
+}
+
+/// Built-in codecs, selectable with `--compress`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    pub fn codec(self) -> &'static dyn Codec {
+        match self {
+            Self::Gzip => &Gzip,
+            Self::Zstd => &Zstd,
+        }
+    }
+}
+
+/// Look up a codec by its `content-encoding` value.
+pub fn codec_for(name: &str) -> Option<&'static dyn Codec> {
+    [&Gzip as &'static dyn Codec, &Zstd]
+        .into_iter()
+        .find(|c| c.name().eq_ignore_ascii_case(name))
+}
+
+/// Payload size before and after encoding (body + attributes, as SQS counts it).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SizeReport {
+    pub original: usize,
+    pub encoded: usize,
+    /// The encoded body was not smaller, so the message was sent as-is
+    pub skipped: bool,
+}
+
+impl SizeReport {
+    pub fn saved_pct(&self) -> f64 {
+        if self.original == 0 {
+            return 0.0;
+        }
+        100.0 * (self.original as f64 - self.encoded as f64) / self.original as f64
+    }
+
+    /// Billed 64 KiB chunks before and after.
+    pub fn chunks(&self) -> (usize, usize) {
+        (
+            self.original.div_ceil(BILLING_CHUNK_BYTES).max(1),
+            self.encoded.div_ceil(BILLING_CHUNK_BYTES).max(1),
+        )
+    }
+
+    pub fn add(&mut self, other: SizeReport) {
+        self.original += other.original;
+        self.encoded += other.encoded;
+    }
+}
+
+/// Compress and base64-wrap the body, tagging it with `content-encoding`.
+/// Bodies that would not get smaller are left unchanged (`skipped`).
+pub fn encode_entry(entry: BatchEntry, codec: &dyn Codec) -> Result<(BatchEntry, SizeReport)> {
+    let original = entry.size();
+    let compressed = codec
+        .compress(entry.body.as_bytes())
+        .with_context(|| format!("{} compression", codec.name()))?;
+    let mut encoded = BatchEntry {
+        body: BASE64.encode(compressed),
+        ..entry.clone()
+    };
+    encoded.attributes.insert(
+        CONTENT_ENCODING_ATTR.to_string(),
+        MessageAttributeValue::builder()
+            .data_type("String")
+            .string_value(codec.name())
+            .build()?,
+    );
+
+    if encoded.size() >= original {
+        let report = SizeReport {
+            original,
+            encoded: original,
+            skipped: true,
+        };
+        return Ok((entry, report));
+    }
+    let report = SizeReport {
+        original,
+        encoded: encoded.size(),
+        skipped: false,
+    };
+    Ok((encoded, report))
+}
+
+/// For an encoded message, a copy with the decoded body and without the
+/// `content-encoding` attribute; `None` for plain messages.
+pub fn decode_message(m: &Message) -> Result<Option<Message>> {
+    let Some(name) = m
+        .message_attributes()
+        .and_then(|a| a.get(CONTENT_ENCODING_ATTR))
+        .and_then(|v| v.string_value())
+    else {
+        return Ok(None);
+    };
+    let codec =
+        codec_for(name).ok_or_else(|| anyhow!("unsupported content-encoding {:?}", name))?;
+
+    let raw = BASE64
+        .decode(m.body().unwrap_or_default())
+        .with_context(|| format!("{} body is not valid base64", codec.name()))?;
+    let bytes = codec
+        .decompress(&raw)
+        .with_context(|| format!("{} decompression", codec.name()))?;
+    let body = String::from_utf8(bytes).map_err(|_| anyhow!("decoded body is not valid UTF-8"))?;
+
+    let mut plain = m.clone();
+    plain.body = Some(body);
+    if let Some(attrs) = plain.message_attributes.as_mut() {
+        attrs.remove(CONTENT_ENCODING_ATTR);
+    }
+    Ok(Some(plain))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    /// What a consumer receives for `entry`.
+    fn received(entry: &BatchEntry) -> Message {
+        Message::builder()
+            .message_id("m1")
+            .body(&entry.body)
+            .set_message_attributes(Some(entry.attributes.clone()))
+            .build()
+    }
+
+    fn string(v: &str) -> MessageAttributeValue {
+        MessageAttributeValue::builder()
+            .data_type("String")
+            .string_value(v)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        let body = r#"{"event":"order.created","items":[1,2,3]}"#.repeat(200);
+        for compression in [Compression::Gzip, Compression::Zstd] {
+            let codec = compression.codec();
+            let entry = BatchEntry {
+                body: body.clone(),
+                attributes: HashMap::from([("tenant".to_string(), string("acme"))]),
+                ..Default::default()
+            };
+            let (encoded, report) = encode_entry(entry, codec).unwrap();
+            assert!(!report.skipped, "{}", codec.name());
+            assert!(report.encoded < report.original, "{}", codec.name());
+            assert_eq!(report.encoded, encoded.size());
+
+            let decoded = decode_message(&received(&encoded)).unwrap().unwrap();
+            assert_eq!(decoded.body(), Some(body.as_str()), "{}", codec.name());
+            let attrs = decoded.message_attributes().unwrap();
+            assert!(!attrs.contains_key(CONTENT_ENCODING_ATTR));
+            assert_eq!(attrs["tenant"].string_value(), Some("acme"));
+        }
+    }
+
+    #[test]
+    fn incompressible_body_is_sent_as_is() {
+        let entry = BatchEntry {
+            body: "hi".to_string(),
+            ..Default::default()
+        };
+        let (encoded, report) = encode_entry(entry, &Zstd).unwrap();
+        assert!(report.skipped);
+        assert_eq!(encoded.body, "hi");
+        assert!(decode_message(&received(&encoded)).unwrap().is_none());
+    }
+
+    #[test]
+    fn unknown_content_encoding() {
+        let m = Message::builder()
+            .body("aGVsbG8=")
+            .message_attributes(CONTENT_ENCODING_ATTR, string("br"))
+            .build();
+        let err = decode_message(&m).unwrap_err();
+        assert_eq!(err.to_string(), r#"unsupported content-encoding "br""#);
+    }
+
+    #[test]
+    fn corrupt_body() {
+        let m = Message::builder()
+            .body("not base64!")
+            .message_attributes(CONTENT_ENCODING_ATTR, string("gzip"))
+            .build();
+        assert!(decode_message(&m).is_err());
+    }
+}
@@ -23,14 +23,196 @@ pub struct RuntimeConfig {
 pub struct SqsConfig {
     pub queue_name: Option<String>,
     pub endpoint_url: Option<String>,
+    /// Seconds a received message stays hidden (0..=43200)
     pub visibility_timeout_secs: Option<i32>,
+    /// Delivery delay for every new message (0..=900)
+    pub delay_secs: Option<i32>,
+    /// How long SQS keeps unconsumed messages (60..=1209600, AWS default 4 days)
+    pub message_retention_period_secs: Option<i32>,
+    /// Largest accepted message in bytes (1024..=262144)
+    pub maximum_message_size: Option<i32>,
+    /// Queue-level long polling default for ReceiveMessage (0..=20)
+    pub receive_message_wait_time_secs: Option<i32>,
     pub fifo: Option<bool>,
     pub content_based_dedup: Option<bool>,
+    /// FIFO only: deduplicate per queue or per message group
+    pub deduplication_scope: Option<DeduplicationScope>,
+    /// FIFO only: `perMessageGroupId` (with `deduplication_scope = "messageGroup"`)
+    /// enables high-throughput mode
+    pub fifo_throughput_limit: Option<FifoThroughputLimit>,
+    /// Server-side encryption; unset keeps the AWS default for new queues
+    pub sse: Option<SseMode>,
+    /// SSE-KMS key id, ARN or alias (defaults to "alias/aws/sqs")
+    pub kms_master_key_id: Option<String>,
+    /// SSE-KMS: how long SQS reuses a data key before calling KMS again (60..=86400)
+    pub kms_data_key_reuse_period_secs: Option<i32>,
+    /// Optional dead-letter queue wired to the main queue via RedrivePolicy
+    pub dlq: Option<DlqConfig>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DeduplicationScope {
+    Queue,
+    MessageGroup,
+}
+
+impl DeduplicationScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeduplicationScope::Queue => "queue",
+            DeduplicationScope::MessageGroup => "messageGroup",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FifoThroughputLimit {
+    PerQueue,
+    PerMessageGroupId,
+}
+
+impl FifoThroughputLimit {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FifoThroughputLimit::PerQueue => "perQueue",
+            FifoThroughputLimit::PerMessageGroupId => "perMessageGroupId",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SseMode {
+    /// SSE-SQS: keys owned and managed by SQS
+    Sqs,
+    /// SSE-KMS: a KMS key (AWS managed or customer managed)
+    Kms,
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct DlqConfig {
+    /// DLQ name; defaults to "<queue_name>-dlq" (".fifo" is appended for FIFO queues)
+    pub name: Option<String>,
+    /// Receives before SQS moves a message to the DLQ (1..=1000)
+    pub max_receive_count: Option<i32>,
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct SnsConfig {
+    pub topic_name: Option<String>,
+    pub endpoint_url: Option<String>,
+    pub fifo: Option<bool>,
+    pub content_based_dedup: Option<bool>,
+    /// Queues subscribed to the topic by `bootstrap`
+    #[serde(default)]
+    pub subscriptions: Vec<SubscriptionConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SubscriptionConfig {
+    pub queue_name: String,
+    /// Deliver the raw message instead of the SNS JSON envelope
+    pub raw_message_delivery: Option<bool>,
+    /// SNS filter policy (TOML table, sent to SNS as JSON)
+    pub filter_policy: Option<serde_json::Value>,
+    /// What the filter policy is matched against (defaults to MessageAttributes)
+    pub filter_policy_scope: Option<FilterPolicyScope>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+pub enum FilterPolicyScope {
+    #[default]
+    #[value(name = "MessageAttributes")]
+    MessageAttributes,
+    #[value(name = "MessageBody")]
+    MessageBody,
+}
+
+impl FilterPolicyScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FilterPolicyScope::MessageAttributes => "MessageAttributes",
+            FilterPolicyScope::MessageBody => "MessageBody",
+        }
+    }
 }
 
 #[derive(Debug, Clone, Deserialize, Default)]
 pub struct RecvConfig {
     pub wait_secs: Option<i32>,
+    #[serde(default)]
+    pub heartbeat: HeartbeatConfig,
+    #[serde(default)]
+    pub shutdown: ShutdownConfig,
+    /// Backoff applied to failed messages; without it they wait out the visibility timeout
+    pub retry: Option<RetryConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct RetryConfig {
+    /// Delay before the first retry (defaults to 1)
+    pub base_delay_secs: Option<f64>,
+    /// Growth factor per receive (defaults to 2.0)
+    pub multiplier: Option<f64>,
+    /// Upper bound for the delay (defaults to 300, SQS allows up to 43200)
+    pub max_delay_secs: Option<f64>,
+    /// Fraction of the delay that is randomized, 0.0..=1.0 (defaults to 0.2)
+    pub jitter: Option<f64>,
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct HeartbeatConfig {
+    /// How often to extend visibility (defaults to half of extend_secs)
+    pub interval_secs: Option<u64>,
+    /// New visibility timeout set on each beat (defaults to [sqs].visibility_timeout_secs or 30)
+    pub extend_secs: Option<i32>,
+    /// Stop extending once a handler has run this long (defaults to 300)
+    pub max_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct ShutdownConfig {
+    /// How long to wait for in-flight handlers after Ctrl+C/SIGTERM (defaults to 30)
+    pub grace_secs: Option<u64>,
+    /// Reset visibility to 0 for messages still unsettled at exit, so they are redelivered
+    /// right away instead of after the visibility timeout (defaults to false)
+    pub release_in_flight: Option<bool>,
+}
+
+/// Large payload offload to S3 (extended client pattern); `bucket` turns it on.
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct OffloadConfig {
+    pub bucket: Option<String>,
+    pub endpoint_url: Option<String>,
+    /// Offload messages larger than this, body + attributes (defaults to 262144 = 256 KiB)
+    pub threshold_bytes: Option<usize>,
+    /// Offload every message regardless of size (defaults to false)
+    pub always: Option<bool>,
+    /// Prefix for object keys (defaults to "payloads/")
+    pub key_prefix: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EncryptionMode {
+    /// 32-byte AES key read from `key_file`
+    Local,
+    /// Data key from KMS `GenerateDataKey` under `key_id`
+    Kms,
+}
+
+/// Client-side envelope encryption of message bodies; `mode` turns it on.
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct EncryptionConfig {
+    pub mode: Option<EncryptionMode>,
+    /// Local mode: raw 32-byte key or its base64 encoding
+    pub key_file: Option<String>,
+    /// KMS key id, ARN or alias; in local mode a label for the key (defaults to "local")
+    pub key_id: Option<String>,
+    /// KMS endpoint override
+    pub endpoint_url: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -39,7 +221,13 @@ pub struct AppConfig {
     #[serde(default)]
     pub sqs: SqsConfig,
     #[serde(default)]
+    pub sns: SnsConfig,
+    #[serde(default)]
     pub recv: RecvConfig,
+    #[serde(default)]
+    pub offload: OffloadConfig,
+    #[serde(default)]
+    pub encryption: EncryptionConfig,
 }
 
 impl AppConfig {
@@ -60,10 +248,10 @@ impl AppConfig {
         builder = builder.add_source(File::with_name(root_config));
 
         // Lab config (optional, overrides root)
-        if let Some(lab_path) = lab_config {
-            if Path::new(lab_path).exists() {
-                builder = builder.add_source(File::with_name(lab_path));
-            }
+        if let Some(lab_path) = lab_config
+            && Path::new(lab_path).exists()
+        {
+            builder = builder.add_source(File::with_name(lab_path));
         }
 
         // Environment overrides (e.g., APP_RUNTIME__REGION=eu-west-1)
@@ -90,21 +278,29 @@ impl AppConfig {
     }
 }
 
-pub async fn build_sqs_client(cfg: &AppConfig) -> Result<sqs::Client> {
+/// Load the shared AWS config for `cfg`.
+/// `endpoint_url` is the service-specific endpoint override (if any).
+pub(crate) async fn load_sdk_config(
+    cfg: &AppConfig,
+    endpoint_url: Option<&str>,
+) -> aws_config::SdkConfig {
     let mut loader = aws_config::defaults(BehaviorVersion::latest())
         .region(aws_config::Region::new(cfg.runtime.region.clone()));
 
     // If we're on LocalStack (runtime=local) OR an explicit endpoint is provided,
     // use static dummy creds to bypass SSO/profile resolution.
-    let using_localstack =
-        matches!(cfg.runtime.mode, RuntimeMode::Local) || cfg.sqs.endpoint_url.is_some();
+    let using_localstack = matches!(cfg.runtime.mode, RuntimeMode::Local) || endpoint_url.is_some();
 
     if using_localstack {
         let creds = Credentials::new("test", "test", None, None, "localstack");
         loader = loader.credentials_provider(SharedCredentialsProvider::new(creds));
     }
 
-    let shared_cfg = loader.load().await;
+    loader.load().await
+}
+
+pub async fn build_sqs_client(cfg: &AppConfig) -> Result<sqs::Client> {
+    let shared_cfg = load_sdk_config(cfg, cfg.sqs.endpoint_url.as_deref()).await;
 
     let mut b = sqs::config::Builder::from(&shared_cfg);
     if let Some(ep) = &cfg.sqs.endpoint_url {
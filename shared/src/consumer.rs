@@ -0,0 +1,1230 @@
+//! Reusable SQS consumer: the runner owns polling, acknowledgements, visibility
+//! changes and logging; binaries only implement [`Handler`].
+
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use aws_sdk_sqs::Client;
+use aws_sdk_sqs::types::{Message, MessageSystemAttributeName};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore, mpsc, watch};
+use tokio::task::JoinSet;
+use tracing::warn;
+
+use crate::batch;
+use crate::cli::ConsumerArgs;
+use crate::codec;
+use crate::config::AppConfig;
+use crate::encryption::{self, DecryptionError, Envelope};
+use crate::fifo::{self, Order, OrderVerifier};
+use crate::heartbeat::Heartbeat;
+use crate::offload::{Offloader, S3Pointer};
+use crate::retry::{self, RetryPolicy};
+use crate::{shutdown, sqs};
+
+/// What the runner should do with a message once the handler is done with it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ack {
+    /// Processed: delete the message (acknowledge)
+    Delete,
+    /// Make the message visible again after the given delay
+    Retry(Duration),
+    /// Do nothing; the message reappears when its visibility timeout lapses
+    Leave,
+    /// Copied elsewhere as received: delete the message but keep its offloaded
+    /// payload, which the copy still points at
+    Moved,
+}
+
+/// Per-message processing logic.
+/// Returning `Err` counts as a failure: the message is backed off with the
+/// retry policy if one is configured, otherwise left for redelivery.
+pub trait Handler: Send + Sync {
+    fn handle(&self, msg: &Message) -> impl Future<Output = Result<Ack>> + Send;
+
+    /// What the runner calls: `received` is the message as it came off the queue
+    /// (pointer body, ciphertext, compressed), `msg` the one [`Handler::handle`]
+    /// gets. Override it to copy the original elsewhere.
+    fn handle_received(
+        &self,
+        received: &Message,
+        msg: &Message,
+    ) -> impl Future<Output = Result<Ack>> + Send {
+        let _ = received;
+        self.handle(msg)
+    }
+
+    /// Called instead of [`Handler::handle`] for an encrypted message that cannot
+    /// be opened; `msg` is the message as received. The default leaves it, so a
+    /// DLQ's maxReceiveCount moves it away.
+    fn poison(
+        &self,
+        msg: &Message,
+        err: DecryptionError,
+    ) -> impl Future<Output = Result<Ack>> + Send {
+        let _ = (msg, err);
+        async { Ok(Ack::Leave) }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ConsumerOptions {
+    /// Log prefix, e.g. "recv" prints "[recv] ..."
+    pub tag: String,
+    pub wait_secs: i32,
+    /// Messages per ReceiveMessage call (1..=10)
+    pub batch_size: i32,
+    /// Never delete, even on `Ack::Delete` (observe redelivery)
+    pub no_delete: bool,
+    pub heartbeat: Option<Heartbeat>,
+    pub retry: Option<RetryPolicy>,
+    /// Concurrent pollers; more than one switches to pool mode
+    pub workers: usize,
+    /// Bound on messages being handled at once; setting it switches to pool mode
+    pub max_in_flight: Option<usize>,
+    /// FIFO: one serial lane per MessageGroupId (implies pool mode)
+    pub per_group: bool,
+    /// FIFO: check SequenceNumber order per group before each handler call
+    pub verify_order: bool,
+    /// Interval of the stats report in pool mode
+    pub report_every: Duration,
+    /// Stop polling after this many received messages
+    pub max_messages: Option<u64>,
+    /// Stop polling once no message arrived for this long
+    pub idle_exit: Option<Duration>,
+    /// How long in-flight handlers may finish after Ctrl+C/SIGTERM
+    pub shutdown_grace: Duration,
+    /// Reset visibility to 0 for messages still unsettled at exit
+    pub release_on_exit: bool,
+}
+
+/// Lifecycle of a running consumer; it only ever moves forward.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Phase {
+    Running,
+    /// Polling stopped (max messages/idle); everything received is still handled
+    Draining,
+    /// Shutdown signal: polling stopped and messages not yet started are skipped
+    Interrupted,
+    /// Grace period over (or a second signal): stop waiting for handlers
+    Expired,
+}
+
+/// A message acknowledged by its handler, waiting to be deleted.
+struct Settled {
+    message_id: String,
+    receipt_handle: String,
+    /// Offloaded body to remove from S3 once the message is deleted
+    payload: Option<S3Pointer>,
+}
+
+/// How `handle_one` left a message.
+enum Handled {
+    /// Acknowledged; carries what still has to be deleted (nothing with `--no-delete`)
+    Done(Option<Settled>),
+    /// Failed, retried, left or poisoned: it will be delivered again
+    Pending,
+    /// Not started because a shutdown signal came first
+    Skipped,
+}
+
+/// A lane stops after this long without messages for its group.
+const LANE_IDLE: Duration = Duration::from_secs(30);
+
+type Lane = mpsc::UnboundedSender<(Message, OwnedSemaphorePermit)>;
+type Lanes = Arc<Mutex<HashMap<String, Lane>>>;
+
+impl ConsumerOptions {
+    /// Defaults from `[recv]`: wait time and retry policy; batch size 1, no heartbeat,
+    /// sequential processing.
+    pub fn from_config(tag: &str, cfg: &AppConfig) -> Self {
+        Self {
+            tag: tag.to_string(),
+            wait_secs: cfg.recv_wait_secs(),
+            batch_size: 1,
+            no_delete: false,
+            heartbeat: None,
+            retry: RetryPolicy::from_config(cfg),
+            workers: 1,
+            max_in_flight: None,
+            per_group: false,
+            verify_order: false,
+            report_every: Duration::from_secs(5),
+            max_messages: None,
+            idle_exit: None,
+            shutdown_grace: Duration::from_secs(cfg.recv.shutdown.grace_secs.unwrap_or(30)),
+            release_on_exit: cfg.recv.shutdown.release_in_flight.unwrap_or(false),
+        }
+    }
+
+    /// `from_config` with the shared consumer flags applied on top.
+    pub fn from_args(tag: &str, cfg: &AppConfig, args: &ConsumerArgs) -> Result<Self> {
+        let heartbeat = args
+            .heartbeat
+            .then(|| Heartbeat::from_config(cfg))
+            .transpose()?;
+        Ok(Self {
+            batch_size: args.batch_size,
+            no_delete: args.no_delete,
+            heartbeat,
+            workers: args.workers as usize,
+            max_in_flight: args.max_in_flight.map(|m| m as usize),
+            per_group: args.per_group,
+            verify_order: args.verify_order,
+            report_every: Duration::from_secs(args.report_every.max(1)),
+            max_messages: args.max_messages,
+            idle_exit: args.idle_exit.map(Duration::from_secs),
+            ..Self::from_config(tag, cfg)
+        })
+    }
+
+    fn pool_mode(&self) -> bool {
+        self.workers > 1 || self.max_in_flight.is_some() || self.per_group
+    }
+
+    fn pool_size(&self) -> usize {
+        self.max_in_flight
+            .unwrap_or(self.workers * self.batch_size as usize)
+            .max(1)
+    }
+}
+
+/// Counters updated by the runner; safe to read while it is running.
+#[derive(Debug, Default)]
+pub struct ConsumerStats {
+    pub received: AtomicU64,
+    /// Messages currently inside a handler
+    pub in_flight: AtomicU64,
+    pub deleted: AtomicU64,
+    pub failed: AtomicU64,
+    /// FIFO group lanes currently running (per-group mode)
+    pub lanes: AtomicU64,
+    /// SequenceNumbers seen going backwards within a group (with verify_order)
+    pub out_of_order: AtomicU64,
+    /// Received but never handled because a shutdown signal came first
+    pub skipped: AtomicU64,
+    /// Made visible again right away: unsettled at exit, or held back behind an
+    /// earlier message of their FIFO group that was not deleted
+    pub released: AtomicU64,
+    /// Encrypted messages that could not be opened (tampered or wrong key)
+    pub poisoned: AtomicU64,
+}
+
+pub struct Consumer<H> {
+    client: Client,
+    queue_url: String,
+    handler: H,
+    opts: ConsumerOptions,
+    stats: ConsumerStats,
+    verifier: OrderVerifier,
+    offload: Option<Offloader>,
+    encryption: Option<Envelope>,
+    phase: watch::Sender<Phase>,
+    stop_reason: OnceLock<String>,
+    /// Receive budget handed out so far (with max_messages)
+    claimed: AtomicU64,
+    last_message: Mutex<Instant>,
+    /// Receipt handle -> message id of everything received but not settled yet
+    unsettled: Mutex<HashMap<String, String>>,
+}
+
+fn lock<T>(m: &Mutex<T>) -> MutexGuard<'_, T> {
+    m.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+impl<H: Handler + 'static> Consumer<H> {
+    pub fn new(
+        client: Client,
+        queue_url: impl Into<String>,
+        handler: H,
+        opts: ConsumerOptions,
+    ) -> Self {
+        Self {
+            client,
+            queue_url: queue_url.into(),
+            handler,
+            opts,
+            stats: ConsumerStats::default(),
+            verifier: OrderVerifier::default(),
+            offload: None,
+            encryption: None,
+            phase: watch::Sender::new(Phase::Running),
+            stop_reason: OnceLock::new(),
+            claimed: AtomicU64::new(0),
+            last_message: Mutex::new(Instant::now()),
+            unsettled: Mutex::default(),
+        }
+    }
+
+    /// Fetch S3-offloaded bodies before the handler runs and delete them after the ack.
+    pub fn with_offload(mut self, offload: Option<Offloader>) -> Self {
+        self.offload = offload;
+        self
+    }
+
+    /// Decrypt client-side encrypted bodies before the handler runs.
+    pub fn with_encryption(mut self, encryption: Option<Envelope>) -> Self {
+        self.encryption = encryption;
+        self
+    }
+
+    pub fn stats(&self) -> &ConsumerStats {
+        &self.stats
+    }
+
+    /// Print the effective settings (heartbeat/retry/pool) under the consumer's tag.
+    pub fn print_settings(&self) {
+        let tag = &self.opts.tag;
+        if let Some(hb) = &self.opts.heartbeat {
+            println!(
+                "[{tag}] heartbeat: every {:?} extend to {}s, give up after {:?}",
+                hb.interval, hb.extend_secs, hb.max_total
+            );
+        }
+        if let Some(rp) = &self.opts.retry {
+            println!(
+                "[{tag}] retry: base={}s x{} cap={}s jitter={}",
+                rp.base_delay_secs, rp.multiplier, rp.max_delay_secs, rp.jitter
+            );
+        }
+        if self.opts.pool_mode() {
+            println!(
+                "[{tag}] pool: workers={} max_in_flight={} per_group={}",
+                self.opts.workers,
+                self.opts.pool_size(),
+                self.opts.per_group
+            );
+        }
+        if let Some(off) = &self.offload {
+            println!(
+                "[{tag}] offload: fetching S3 payloads (bucket {})",
+                off.bucket
+            );
+        }
+        if let Some(env) = &self.encryption {
+            println!(
+                "[{tag}] encryption: decrypting bodies ({:?}, key {})",
+                env.mode(),
+                env.key_id()
+            );
+        }
+        if self.opts.verify_order {
+            println!("[{tag}] verifying SequenceNumber order per MessageGroupId");
+        }
+        if let Some(max) = self.opts.max_messages {
+            println!("[{tag}] exit after {} message(s)", max);
+        }
+        if let Some(idle) = self.opts.idle_exit {
+            println!("[{tag}] exit after {}s without messages", idle.as_secs());
+        }
+        println!(
+            "[{tag}] shutdown: grace={}s release_in_flight={}",
+            self.opts.shutdown_grace.as_secs(),
+            self.opts.release_on_exit
+        );
+    }
+
+    /// Poll until stopped, sequentially or with a worker pool depending on the options.
+    ///
+    /// Polling stops on Ctrl+C/SIGTERM, after `max_messages` or after `idle_exit`.
+    /// Messages already being handled are finished (within `shutdown_grace` after a
+    /// signal); whatever is still unsettled at exit is released if configured, then a
+    /// summary is printed.
+    pub async fn run(self) -> Result<()> {
+        let this = Arc::new(self);
+        let started = Instant::now();
+        let signals = tokio::spawn(this.clone().watch_signals());
+
+        let work = async {
+            if this.opts.pool_mode() {
+                this.clone().run_pool().await
+            } else {
+                this.run_sequential().await
+            }
+        };
+        let result = tokio::select! {
+            res = work => res,
+            _ = this.reached(Phase::Expired) => {
+                warn!(
+                    "shutdown grace period over with {} message(s) still in flight",
+                    this.stats.in_flight.load(Ordering::Relaxed)
+                );
+                Ok(())
+            }
+        };
+        signals.abort();
+        if result.is_err() {
+            this.advance(Phase::Draining, "error");
+        }
+
+        this.release_unsettled().await;
+        this.print_summary(started.elapsed());
+        result
+    }
+
+    async fn run_sequential(&self) -> Result<()> {
+        while self.phase() == Phase::Running {
+            self.poll_once().await?;
+        }
+        Ok(())
+    }
+
+    /// One ReceiveMessage call plus processing of everything it returned.
+    /// Once a FIFO message is not deleted, the rest of its group in the batch is
+    /// released instead of handled, so the group stays in order.
+    /// Returns the number of messages received.
+    pub async fn poll_once(&self) -> Result<usize> {
+        let msgs = self.fetch(self.opts.batch_size).await?;
+
+        let mut acks = Vec::new();
+        let mut blocked = HashSet::new();
+        for (idx, m) in msgs.iter().enumerate() {
+            if let Some(group) = fifo::group_id(m)
+                && blocked.contains(group)
+            {
+                self.hold_back(m, group).await;
+                continue;
+            }
+            // Acknowledged messages waiting for the batch delete, this message and
+            // the rest of the batch are all still in flight
+            let in_flight: Vec<String> = acks
+                .iter()
+                .map(|a: &Settled| a.receipt_handle.clone())
+                .chain(
+                    msgs[idx..]
+                        .iter()
+                        .filter_map(|m| m.receipt_handle().map(str::to_string)),
+                )
+                .collect();
+            match self.handle_one(m, &in_flight).await {
+                Handled::Done(ack) => acks.extend(ack),
+                Handled::Pending => blocked.extend(fifo::group_id(m)),
+                Handled::Skipped => {}
+            }
+        }
+        self.delete(&acks).await?;
+        Ok(msgs.len())
+    }
+
+    fn phase(&self) -> Phase {
+        *self.phase.borrow()
+    }
+
+    /// Move to `to` unless already there or further; the first reason given is kept.
+    fn advance(&self, to: Phase, reason: &str) {
+        self.stop_reason.get_or_init(|| reason.to_string());
+        self.phase.send_if_modified(|p| {
+            let forward = to > *p;
+            if forward {
+                *p = to;
+            }
+            forward
+        });
+    }
+
+    async fn reached(&self, phase: Phase) {
+        let mut rx = self.phase.subscribe();
+        let _ = rx.wait_for(|p| *p >= phase).await;
+    }
+
+    /// First signal: stop polling and skip messages not started yet.
+    /// Second signal or end of the grace period: stop waiting for handlers.
+    async fn watch_signals(self: Arc<Self>) {
+        let tag = &self.opts.tag;
+        let sig = shutdown::signal().await;
+        println!(
+            "[{tag}] {sig}: stopping, waiting up to {}s for {} in-flight message(s) (repeat to force)",
+            self.opts.shutdown_grace.as_secs(),
+            self.stats.in_flight.load(Ordering::Relaxed)
+        );
+        self.advance(Phase::Interrupted, sig);
+
+        tokio::select! {
+            _ = tokio::time::sleep(self.opts.shutdown_grace) => {}
+            sig = shutdown::signal() => println!("[{tag}] {sig} again: not waiting any longer"),
+        }
+        self.advance(Phase::Expired, sig);
+    }
+
+    /// Receive up to `max` messages within the `max_messages` budget and track them as
+    /// unsettled. Returns nothing once polling has stopped.
+    async fn fetch(&self, max: i32) -> Result<Vec<Message>> {
+        if self.phase() >= Phase::Draining {
+            return Ok(Vec::new());
+        }
+        let want = self.claim(max);
+        if want == 0 {
+            // Budget is held by another poller's receive; it is handed back if unused
+            tokio::select! {
+                _ = tokio::time::sleep(Duration::from_millis(200)) => {}
+                _ = self.reached(Phase::Draining) => {}
+            }
+            return Ok(Vec::new());
+        }
+
+        let msgs = tokio::select! {
+            msgs = self.receive(want) => msgs,
+            _ = self.reached(Phase::Draining) => Ok(Vec::new()),
+        };
+        let got = msgs.as_ref().map_or(0, Vec::len) as u64;
+        self.claimed.fetch_sub(want as u64 - got, Ordering::Relaxed);
+        let msgs = msgs?;
+
+        if msgs.is_empty() {
+            if let Some(idle) = self.opts.idle_exit
+                && lock(&self.last_message).elapsed() >= idle
+            {
+                self.advance(
+                    Phase::Draining,
+                    &format!("no messages for {}s", idle.as_secs()),
+                );
+            }
+        } else {
+            *lock(&self.last_message) = Instant::now();
+            let mut unsettled = lock(&self.unsettled);
+            for m in &msgs {
+                if let Some(rh) = m.receipt_handle() {
+                    let mid = m.message_id().unwrap_or("unknown");
+                    unsettled.insert(rh.to_string(), mid.to_string());
+                }
+            }
+        }
+
+        if let Some(max) = self.opts.max_messages
+            && self.stats.received.load(Ordering::Relaxed) >= max
+        {
+            self.advance(Phase::Draining, &format!("received {} message(s)", max));
+        }
+        Ok(msgs)
+    }
+
+    /// Reserve up to `want` messages of the `max_messages` budget.
+    fn claim(&self, want: i32) -> i32 {
+        let Some(max) = self.opts.max_messages else {
+            return want;
+        };
+        let mut granted = 0;
+        let _ = self
+            .claimed
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |claimed| {
+                granted = max.saturating_sub(claimed).min(want as u64);
+                (granted > 0).then_some(claimed + granted)
+            });
+        granted as i32
+    }
+
+    async fn receive(&self, max: i32) -> Result<Vec<Message>> {
+        let out = self
+            .client
+            .receive_message()
+            .queue_url(&self.queue_url)
+            .max_number_of_messages(max)
+            .wait_time_seconds(self.opts.wait_secs)
+            // request *all* user attributes
+            .message_attribute_names("All")
+            // system attrs: ApproximateReceiveCount drives backoff, MessageGroupId etc. for FIFO
+            .message_system_attribute_names(MessageSystemAttributeName::All)
+            .send()
+            .await
+            .context("receive_message")?;
+
+        let msgs = out.messages.unwrap_or_default();
+        self.stats
+            .received
+            .fetch_add(msgs.len() as u64, Ordering::Relaxed);
+        Ok(msgs)
+    }
+
+    /// Run the handler (under the heartbeat, if any) and apply its outcome.
+    /// For `Ack::Delete`/`Ack::Moved` the result carries what to delete.
+    async fn handle_one(&self, m: &Message, in_flight: &[String]) -> Handled {
+        let tag = &self.opts.tag;
+        let mid = m.message_id().unwrap_or("unknown");
+
+        if self.phase() >= Phase::Interrupted {
+            self.stats.skipped.fetch_add(1, Ordering::Relaxed);
+            println!("[{tag}] shutting down: not starting message_id={}", mid);
+            return Handled::Skipped;
+        }
+        if self.opts.verify_order {
+            self.verify(m);
+        }
+
+        self.stats.in_flight.fetch_add(1, Ordering::Relaxed);
+        let work = async {
+            // Pointer messages are handed to the handler with the S3 payload as body
+            let resolved = match &self.offload {
+                Some(off) => off.resolve(m).await?,
+                None => None,
+            };
+            let msg = resolved.as_ref().map_or(m, |(full, _)| full);
+            let payload = resolved.as_ref().map(|(_, ptr)| ptr.clone());
+            // Undo the send path in reverse: compress, encrypt, offload
+            let decrypted = match encryption::open(self.encryption.as_ref(), msg).await {
+                Ok(decrypted) => decrypted,
+                Err(e) => {
+                    // Retrying cannot help; the handler decides, not the backoff
+                    let err = e.downcast::<DecryptionError>()?;
+                    self.on_poison(m, &err);
+                    return Ok((self.handler.poison(m, err).await?, payload));
+                }
+            };
+            let msg = decrypted.as_ref().unwrap_or(msg);
+            let decoded = codec::decode_message(msg)?;
+            let msg = decoded.as_ref().unwrap_or(msg);
+            let ack = self.handler.handle_received(m, msg).await?;
+            Ok::<_, anyhow::Error>((ack, payload))
+        };
+        let result = match &self.opts.heartbeat {
+            Some(hb) => hb.run(&self.client, &self.queue_url, in_flight, work).await,
+            None => work.await,
+        };
+        self.stats.in_flight.fetch_sub(1, Ordering::Relaxed);
+        if let Some(rh) = m.receipt_handle() {
+            lock(&self.unsettled).remove(rh);
+        }
+
+        match result {
+            Ok((Ack::Delete | Ack::Moved, _)) if self.opts.no_delete => {
+                warn!("--no-delete set; not deleting message_id={}", mid);
+                return Handled::Done(None);
+            }
+            Ok((ack @ (Ack::Delete | Ack::Moved), payload)) => match m.receipt_handle() {
+                Some(rh) => {
+                    return Handled::Done(Some(Settled {
+                        message_id: mid.to_string(),
+                        receipt_handle: rh.to_string(),
+                        payload: payload.filter(|_| ack == Ack::Delete),
+                    }));
+                }
+                None => warn!("missing receipt_handle; cannot delete"),
+            },
+            Ok((Ack::Retry(after), _)) => {
+                self.retry_after(m, after).await;
+                println!(
+                    "[{tag}] retry: message_id={} retry_in={}s",
+                    mid,
+                    after.as_secs()
+                );
+            }
+            Ok((Ack::Leave, _)) => {
+                println!("[{tag}] left message_id={} for redelivery", mid);
+            }
+            Err(e) => self.on_failure(m, e).await,
+        }
+        Handled::Pending
+    }
+
+    /// Make `m` visible again without handling it: an earlier message of `group`
+    /// was not deleted and has to be processed first.
+    async fn hold_back(&self, m: &Message, group: &str) {
+        let tag = &self.opts.tag;
+        let mid = m.message_id().unwrap_or("unknown");
+        let Some(rh) = m.receipt_handle() else {
+            warn!("missing receipt_handle; cannot release");
+            return;
+        };
+        lock(&self.unsettled).remove(rh);
+        match sqs::change_visibility(&self.client, &self.queue_url, rh, 0).await {
+            Ok(()) => {
+                self.stats.released.fetch_add(1, Ordering::Relaxed);
+                println!(
+                    "[{tag}] held back message_id={} (an earlier message of group {} was not deleted)",
+                    mid, group
+                );
+            }
+            Err(e) => warn!("failed to release message_id={}: {e:#}", mid),
+        }
+    }
+
+    /// Make messages received but never settled visible again (if configured).
+    async fn release_unsettled(&self) {
+        let tag = &self.opts.tag;
+        let unsettled: Vec<(String, String)> = lock(&self.unsettled).drain().collect();
+        if unsettled.is_empty() {
+            return;
+        }
+        if !self.opts.release_on_exit {
+            println!(
+                "[{tag}] {} unsettled message(s) become visible again after the visibility timeout",
+                unsettled.len()
+            );
+            return;
+        }
+        for (rh, mid) in unsettled {
+            match sqs::change_visibility(&self.client, &self.queue_url, &rh, 0).await {
+                Ok(()) => {
+                    self.stats.released.fetch_add(1, Ordering::Relaxed);
+                    println!("[{tag}] released message_id={} (visible now)", mid);
+                }
+                Err(e) => warn!("failed to release message_id={}: {e:#}", mid),
+            }
+        }
+    }
+
+    fn print_summary(&self, elapsed: Duration) {
+        let tag = &self.opts.tag;
+        let reason = self.stop_reason.get().map_or("stopped", String::as_str);
+        let order = if self.opts.verify_order {
+            format!(
+                " out_of_order={}",
+                self.stats.out_of_order.load(Ordering::Relaxed)
+            )
+        } else {
+            String::new()
+        };
+        println!(
+            "[{tag}] stopped ({}) after {:.1}s: received={} deleted={} failed={} poisoned={} skipped={} released={}{}",
+            reason,
+            elapsed.as_secs_f64(),
+            self.stats.received.load(Ordering::Relaxed),
+            self.stats.deleted.load(Ordering::Relaxed),
+            self.stats.failed.load(Ordering::Relaxed),
+            self.stats.poisoned.load(Ordering::Relaxed),
+            self.stats.skipped.load(Ordering::Relaxed),
+            self.stats.released.load(Ordering::Relaxed),
+            order
+        );
+    }
+
+    fn verify(&self, m: &Message) {
+        let tag = &self.opts.tag;
+        let mid = m.message_id().unwrap_or("unknown");
+        match self.verifier.observe(m) {
+            Order::OutOfOrder { previous } => {
+                self.stats.out_of_order.fetch_add(1, Ordering::Relaxed);
+                warn!(
+                    "out-of-order message_id={} group={} sequence={} < previous={}",
+                    mid,
+                    fifo::group_id(m).unwrap_or("-"),
+                    fifo::sequence_number(m).unwrap_or_default(),
+                    previous
+                );
+                println!("[{tag}] ORDER VIOLATION message_id={}", mid);
+            }
+            Order::Redelivered => {
+                println!("[{tag}] redelivered message_id={} (same sequence)", mid);
+            }
+            Order::InOrder | Order::Unordered => {}
+        }
+    }
+
+    /// Handle one message and delete it right away if acknowledged (pool and lane mode).
+    /// Returns false when the message will be delivered again.
+    async fn settle(&self, m: &Message) -> bool {
+        let own: Vec<String> = m.receipt_handle().map(str::to_string).into_iter().collect();
+        match self.handle_one(m, &own).await {
+            Handled::Done(Some(ack)) => {
+                if let Err(e) = self.delete(&[ack]).await {
+                    warn!("delete failed: {e:#}");
+                }
+                true
+            }
+            Handled::Done(None) | Handled::Skipped => true,
+            Handled::Pending => false,
+        }
+    }
+
+    async fn retry_after(&self, m: &Message, after: Duration) {
+        let Some(rh) = m.receipt_handle() else {
+            warn!("missing receipt_handle; cannot change visibility");
+            return;
+        };
+        let secs = after.as_secs().min(43_200) as i32;
+        if let Err(e) = sqs::change_visibility(&self.client, &self.queue_url, rh, secs).await {
+            warn!("failed to change visibility: {e:#}");
+        }
+    }
+
+    /// Report a message that can never be opened; what happens to it is up to
+    /// [`Handler::poison`].
+    fn on_poison(&self, m: &Message, err: &DecryptionError) {
+        let tag = &self.opts.tag;
+        let mid = m.message_id().unwrap_or("unknown");
+        self.stats.poisoned.fetch_add(1, Ordering::Relaxed);
+        warn!("poison message_id={}: {}", mid, err);
+        println!(
+            "[{tag}] poison: message_id={} receive_count={} ({})",
+            mid,
+            retry::receive_count(m),
+            err
+        );
+    }
+
+    async fn on_failure(&self, m: &Message, err: anyhow::Error) {
+        let tag = &self.opts.tag;
+        let mid = m.message_id().unwrap_or("unknown");
+        let count = retry::receive_count(m);
+        self.stats.failed.fetch_add(1, Ordering::Relaxed);
+        warn!("handler failed for message_id={}: {err:#}", mid);
+
+        match &self.opts.retry {
+            Some(rp) => match rp.backoff(&self.client, &self.queue_url, m).await {
+                Ok(delay) => println!(
+                    "[{tag}] handler failed: message_id={} receive_count={} retry_in={}s",
+                    mid, count, delay
+                ),
+                Err(e) => warn!("backoff failed for message_id={}: {e:#}", mid),
+            },
+            None => println!(
+                "[{tag}] handler failed: message_id={} receive_count={} (visible again after the visibility timeout)",
+                mid, count
+            ),
+        }
+    }
+
+    async fn delete(&self, acks: &[Settled]) -> Result<()> {
+        if acks.is_empty() {
+            return Ok(());
+        }
+        let tag = &self.opts.tag;
+        println!("[{tag}] deleting {} message(s)...", acks.len());
+        let handles: Vec<String> = acks.iter().map(|a| a.receipt_handle.clone()).collect();
+        let failures = batch::delete_all(&self.client, &self.queue_url, &handles).await?;
+        for (i, ack) in acks.iter().enumerate() {
+            let mid = &ack.message_id;
+            match failures.iter().find(|f| f.index == i) {
+                Some(f) => warn!(
+                    "delete failed for message_id={} code={} message={:?} receipt_handle={}",
+                    mid, f.code, f.message, f.receipt_handle
+                ),
+                None => {
+                    self.stats.deleted.fetch_add(1, Ordering::Relaxed);
+                    println!("[{tag}] deleted message_id={}", mid);
+                    // Only once the message is gone: a redelivery would still need the payload
+                    if let (Some(off), Some(ptr)) = (&self.offload, &ack.payload) {
+                        match off.delete(ptr).await {
+                            Ok(()) => println!("[{tag}] deleted payload {}", ptr),
+                            Err(e) => warn!("{e:#}"),
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Pool mode: `workers` pollers share a semaphore of `max_in_flight` permits.
+    /// A poller only receives as many messages as it holds permits for, so
+    /// receiving pauses while the pool is saturated (backpressure).
+    async fn run_pool(self: Arc<Self>) -> Result<()> {
+        let permits = Arc::new(Semaphore::new(self.opts.pool_size()));
+
+        let lanes: Option<Lanes> = self.opts.per_group.then(Lanes::default);
+
+        let mut pollers = JoinSet::new();
+        for _ in 0..self.opts.workers {
+            let this = self.clone();
+            let permits = permits.clone();
+            let lanes = lanes.clone();
+            pollers.spawn(async move { this.poller(permits, lanes).await });
+        }
+
+        let reporter = tokio::spawn(self.clone().report());
+
+        let mut result = Ok(());
+        while let Some(res) = pollers.join_next().await {
+            if let Err(e) = res.context("poller task panicked").and_then(|r| r) {
+                // Let the other pollers stop and in-flight messages finish first
+                self.advance(Phase::Draining, "poller error");
+                if result.is_ok() {
+                    result = Err(e);
+                }
+            }
+        }
+
+        // Every handler holds a permit: getting them all back means the pool is idle
+        let _ = permits.acquire_many(self.opts.pool_size() as u32).await;
+        reporter.abort();
+        result
+    }
+
+    async fn poller(self: Arc<Self>, permits: Arc<Semaphore>, lanes: Option<Lanes>) -> Result<()> {
+        loop {
+            // Wait for at least one free slot, then take up to batch_size without waiting
+            let first = tokio::select! {
+                permit = permits.clone().acquire_owned() => permit?,
+                _ = self.reached(Phase::Draining) => return Ok(()),
+            };
+            if self.phase() >= Phase::Draining {
+                return Ok(());
+            }
+            let mut held = vec![first];
+            while held.len() < self.opts.batch_size as usize {
+                match permits.clone().try_acquire_owned() {
+                    Ok(p) => held.push(p),
+                    Err(_) => break,
+                }
+            }
+
+            let msgs = self.fetch(held.len() as i32).await?;
+            // Unused permits are released when `held` is dropped
+            for m in msgs {
+                let Some(permit) = held.pop() else { break };
+                match &lanes {
+                    Some(lanes) => self.dispatch(lanes, m, permit),
+                    None => {
+                        let this = self.clone();
+                        tokio::spawn(async move {
+                            this.settle(&m).await;
+                            drop(permit);
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    /// Queue `m` on the serial lane of its MessageGroupId, starting the lane if needed.
+    /// Messages without a group share the "" lane.
+    fn dispatch(self: &Arc<Self>, lanes: &Lanes, m: Message, permit: OwnedSemaphorePermit) {
+        let group = fifo::group_id(&m).unwrap_or_default().to_string();
+        let mut map = lock(lanes);
+
+        // Lanes only drop their receiver after removing themselves under this lock
+        let item = match map.get(&group) {
+            Some(tx) => match tx.send((m, permit)) {
+                Ok(()) => return,
+                Err(mpsc::error::SendError(item)) => item,
+            },
+            None => (m, permit),
+        };
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        let _ = tx.send(item);
+        map.insert(group.clone(), tx);
+        tokio::spawn(self.clone().lane(group, rx, lanes.clone()));
+    }
+
+    /// Process one group's messages strictly in arrival order. When a message is
+    /// not deleted, the ones already queued behind it are released, not handled.
+    async fn lane(
+        self: Arc<Self>,
+        group: String,
+        mut rx: mpsc::UnboundedReceiver<(Message, OwnedSemaphorePermit)>,
+        lanes: Lanes,
+    ) {
+        self.stats.lanes.fetch_add(1, Ordering::Relaxed);
+        loop {
+            match tokio::time::timeout(LANE_IDLE, rx.recv()).await {
+                Ok(Some((m, permit))) => {
+                    let done = self.settle(&m).await;
+                    drop(permit);
+                    if !done && !group.is_empty() {
+                        while let Ok((next, permit)) = rx.try_recv() {
+                            self.hold_back(&next, &group).await;
+                            drop(permit);
+                        }
+                    }
+                }
+                Ok(None) => break,
+                Err(_) => {
+                    let mut map = lock(&lanes);
+                    if rx.is_empty() {
+                        map.remove(&group);
+                        break;
+                    }
+                }
+            }
+        }
+        self.stats.lanes.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    async fn report(self: Arc<Self>) {
+        let tag = &self.opts.tag;
+        let pool = self.opts.pool_size();
+        let mut ticker = tokio::time::interval(self.opts.report_every);
+        ticker.tick().await;
+        let mut last = (Instant::now(), 0u64);
+
+        loop {
+            ticker.tick().await;
+            let deleted = self.stats.deleted.load(Ordering::Relaxed);
+            let elapsed = last.0.elapsed().as_secs_f64();
+            let rate = (deleted - last.1) as f64 / elapsed.max(f64::EPSILON);
+            let lanes = if self.opts.per_group {
+                format!(
+                    " lanes={} out_of_order={}",
+                    self.stats.lanes.load(Ordering::Relaxed),
+                    self.stats.out_of_order.load(Ordering::Relaxed)
+                )
+            } else {
+                String::new()
+            };
+            println!(
+                "[{tag}] stats: in_flight={}/{} received={} deleted={} failed={} rate={:.1} msg/s{}",
+                self.stats.in_flight.load(Ordering::Relaxed),
+                pool,
+                self.stats.received.load(Ordering::Relaxed),
+                deleted,
+                self.stats.failed.load(Ordering::Relaxed),
+                rate,
+                lanes
+            );
+            last = (Instant::now(), deleted);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use aws_sdk_sqs::operation::change_message_visibility::ChangeMessageVisibilityOutput;
+    use aws_sdk_sqs::operation::delete_message_batch::DeleteMessageBatchOutput;
+    use aws_sdk_sqs::operation::receive_message::ReceiveMessageOutput;
+    use aws_smithy_mocks::{RuleMode, mock, mock_client};
+
+    use super::*;
+
+    /// Fails every message whose body is "fail".
+    struct FailOn;
+
+    impl Handler for FailOn {
+        async fn handle(&self, msg: &Message) -> Result<Ack> {
+            match msg.body() {
+                Some("fail") => Err(anyhow::anyhow!("boom")),
+                _ => Ok(Ack::Delete),
+            }
+        }
+    }
+
+    fn fifo_message(n: u32, group: &str, body: &str) -> Message {
+        Message::builder()
+            .message_id(format!("m{n}"))
+            .receipt_handle(format!("rh-{n}"))
+            .body(body)
+            .attributes(MessageSystemAttributeName::MessageGroupId, group)
+            .attributes(MessageSystemAttributeName::SequenceNumber, n.to_string())
+            .build()
+    }
+
+    fn options() -> ConsumerOptions {
+        ConsumerOptions {
+            tag: "test".to_string(),
+            wait_secs: 0,
+            batch_size: 10,
+            no_delete: false,
+            heartbeat: None,
+            retry: None,
+            workers: 1,
+            max_in_flight: None,
+            per_group: false,
+            verify_order: false,
+            report_every: Duration::from_secs(5),
+            max_messages: None,
+            idle_exit: None,
+            shutdown_grace: Duration::from_secs(1),
+            release_on_exit: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn failure_holds_back_the_rest_of_its_group() {
+        let receive = mock!(Client::receive_message).then_output(|| {
+            ReceiveMessageOutput::builder()
+                .messages(fifo_message(1, "g1", "fail"))
+                .messages(fifo_message(2, "g2", "ok"))
+                .messages(fifo_message(3, "g1", "ok"))
+                .build()
+        });
+        // Only the other group's message may be deleted
+        let delete = mock!(Client::delete_message_batch)
+            .match_requests(|req| {
+                req.entries().len() == 1 && req.entries()[0].receipt_handle() == "rh-2"
+            })
+            .then_output(|| {
+                DeleteMessageBatchOutput::builder()
+                    .set_successful(Some(Vec::new()))
+                    .set_failed(Some(Vec::new()))
+                    .build()
+                    .unwrap()
+            });
+        let release = mock!(Client::change_message_visibility)
+            .match_requests(|req| {
+                req.receipt_handle() == Some("rh-3") && req.visibility_timeout() == Some(0)
+            })
+            .then_output(|| ChangeMessageVisibilityOutput::builder().build());
+        let client = mock_client!(
+            aws_sdk_sqs,
+            RuleMode::MatchAny,
+            [&receive, &delete, &release]
+        );
+
+        let consumer = Consumer::new(client, "https://sqs.local/q.fifo", FailOn, options());
+        assert_eq!(consumer.poll_once().await.unwrap(), 3);
+
+        assert_eq!(delete.num_calls(), 1);
+        assert_eq!(release.num_calls(), 1);
+        let stats = consumer.stats();
+        assert_eq!(stats.deleted.load(Ordering::Relaxed), 1);
+        assert_eq!(stats.failed.load(Ordering::Relaxed), 1);
+        assert_eq!(stats.released.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn lane_releases_queued_messages_after_a_failure() {
+        let receive = mock!(Client::receive_message).then_output(|| {
+            ReceiveMessageOutput::builder()
+                .messages(fifo_message(1, "g1", "fail"))
+                .messages(fifo_message(2, "g1", "ok"))
+                .build()
+        });
+        let delete = mock!(Client::delete_message_batch).then_output(|| {
+            DeleteMessageBatchOutput::builder()
+                .set_successful(Some(Vec::new()))
+                .set_failed(Some(Vec::new()))
+                .build()
+                .unwrap()
+        });
+        let release = mock!(Client::change_message_visibility)
+            .match_requests(|req| req.receipt_handle() == Some("rh-2"))
+            .then_output(|| ChangeMessageVisibilityOutput::builder().build());
+        let client = mock_client!(
+            aws_sdk_sqs,
+            RuleMode::MatchAny,
+            [&receive, &delete, &release]
+        );
+
+        let consumer = Arc::new(Consumer::new(
+            client,
+            "https://sqs.local/q.fifo",
+            FailOn,
+            ConsumerOptions {
+                per_group: true,
+                ..options()
+            },
+        ));
+        let lanes = Lanes::default();
+        let permits = Arc::new(Semaphore::new(2));
+        // Queue both before the lane runs, as a poller dispatching one receive does
+        let msgs = consumer.fetch(10).await.unwrap();
+        for m in msgs {
+            let permit = permits.clone().acquire_owned().await.unwrap();
+            consumer.dispatch(&lanes, m, permit);
+        }
+        let _ = permits.acquire_many(2).await.unwrap();
+
+        assert_eq!(delete.num_calls(), 0);
+        assert_eq!(release.num_calls(), 1);
+        assert_eq!(consumer.stats().deleted.load(Ordering::Relaxed), 0);
+    }
+
+    /// Deletes messages that cannot be decrypted.
+    struct DeletePoison;
+
+    impl Handler for DeletePoison {
+        async fn handle(&self, _msg: &Message) -> Result<Ack> {
+            Ok(Ack::Delete)
+        }
+
+        async fn poison(&self, _msg: &Message, _err: DecryptionError) -> Result<Ack> {
+            Ok(Ack::Delete)
+        }
+    }
+
+    #[tokio::test]
+    async fn undecryptable_message_goes_to_the_handler() {
+        let receive = mock!(Client::receive_message).then_output(|| {
+            let key_id = aws_sdk_sqs::types::MessageAttributeValue::builder()
+                .data_type("String")
+                .string_value("k1")
+                .build()
+                .unwrap();
+            ReceiveMessageOutput::builder()
+                .messages(
+                    Message::builder()
+                        .message_id("m1")
+                        .receipt_handle("rh-1")
+                        .body("c2VhbGVk")
+                        .message_attributes(encryption::KEY_ID_ATTR, key_id)
+                        .build(),
+                )
+                .build()
+        });
+        let delete = mock!(Client::delete_message_batch)
+            .match_requests(|req| req.entries()[0].receipt_handle() == "rh-1")
+            .then_output(|| {
+                DeleteMessageBatchOutput::builder()
+                    .set_successful(Some(Vec::new()))
+                    .set_failed(Some(Vec::new()))
+                    .build()
+                    .unwrap()
+            });
+        let client = mock_client!(aws_sdk_sqs, RuleMode::MatchAny, [&receive, &delete]);
+
+        // No [encryption] configured: the message cannot be opened
+        let consumer = Consumer::new(client, "https://sqs.local/q", DeletePoison, options());
+        assert_eq!(consumer.poll_once().await.unwrap(), 1);
+
+        assert_eq!(delete.num_calls(), 1);
+        let stats = consumer.stats();
+        assert_eq!(stats.poisoned.load(Ordering::Relaxed), 1);
+        assert_eq!(stats.failed.load(Ordering::Relaxed), 0);
+        assert_eq!(stats.deleted.load(Ordering::Relaxed), 1);
+    }
+
+    /// Takes longer than the visibility timeout on "slow" bodies.
+    struct Slow;
+
+    impl Handler for Slow {
+        async fn handle(&self, msg: &Message) -> Result<Ack> {
+            if msg.body() == Some("slow") {
+                tokio::time::sleep(Duration::from_secs(10)).await;
+            }
+            Ok(Ack::Delete)
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn heartbeat_covers_acknowledged_messages_until_deleted() {
+        let receive = mock!(Client::receive_message).then_output(|| {
+            ReceiveMessageOutput::builder()
+                .messages(fifo_message(1, "g1", "fast"))
+                .messages(fifo_message(2, "g2", "slow"))
+                .build()
+        });
+        let delete = mock!(Client::delete_message_batch)
+            .match_requests(|req| req.entries().len() == 2)
+            .then_output(|| {
+                DeleteMessageBatchOutput::builder()
+                    .set_successful(Some(Vec::new()))
+                    .set_failed(Some(Vec::new()))
+                    .build()
+                    .unwrap()
+            });
+        // The first message is already acknowledged while the second one runs
+        let extend_first = mock!(Client::change_message_visibility)
+            .match_requests(|req| req.receipt_handle() == Some("rh-1"))
+            .then_output(|| ChangeMessageVisibilityOutput::builder().build());
+        let extend_second = mock!(Client::change_message_visibility)
+            .match_requests(|req| req.receipt_handle() == Some("rh-2"))
+            .then_output(|| ChangeMessageVisibilityOutput::builder().build());
+        let client = mock_client!(
+            aws_sdk_sqs,
+            RuleMode::MatchAny,
+            [&receive, &delete, &extend_first, &extend_second]
+        );
+
+        let opts = ConsumerOptions {
+            batch_size: 2,
+            heartbeat: Some(Heartbeat {
+                interval: Duration::from_secs(1),
+                extend_secs: 3,
+                max_total: Duration::from_secs(60),
+            }),
+            ..options()
+        };
+        let consumer = Consumer::new(client, "https://sqs.local/q.fifo", Slow, opts);
+        assert_eq!(consumer.poll_once().await.unwrap(), 2);
+
+        assert!(
+            extend_first.num_calls() >= 9,
+            "{}",
+            extend_first.num_calls()
+        );
+        assert_eq!(extend_first.num_calls(), extend_second.num_calls());
+        assert_eq!(delete.num_calls(), 1);
+    }
+}
@@ -0,0 +1,558 @@
+//! Client-side envelope encryption: bodies are sealed with AES-256-GCM under a
+//! data key before they leave the producer. The key id, the nonce and (with KMS)
+//! the encrypted data key travel as message attributes; the consumer opens the
+//! body before the handler runs.
+//!
+//! The envelope version, the key id and the `content-encoding` attribute are
+//! bound to the ciphertext as associated data, so changing any of them fails
+//! authentication just like a modified body does.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use anyhow::{Context, Result, anyhow};
+use aws_sdk_kms as kms;
+use aws_sdk_kms::operation::decrypt::DecryptError;
+use aws_sdk_kms::types::DataKeySpec;
+use aws_sdk_sqs::primitives::Blob;
+use aws_sdk_sqs::types::{Message, MessageAttributeValue};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use tokio::sync::OnceCell;
+use tracing::info;
+
+use crate::batch::BatchEntry;
+use crate::codec::CONTENT_ENCODING_ATTR;
+use crate::config::{AppConfig, EncryptionMode, load_sdk_config};
+
+/// String attribute: local key label or KMS key ARN the data key belongs to.
+pub const KEY_ID_ATTR: &str = "encryption-key-id";
+/// Binary attribute: the 12-byte AES-GCM nonce.
+pub const NONCE_ATTR: &str = "encryption-nonce";
+/// Binary attribute (KMS only): the data key encrypted under the KMS key.
+pub const DATA_KEY_ATTR: &str = "encryption-data-key";
+/// String attribute: envelope format version, see [`ENVELOPE_VERSION`].
+pub const VERSION_ATTR: &str = "encryption-version";
+
+/// Current envelope format; messages with another version are rejected.
+pub const ENVELOPE_VERSION: &str = "1";
+
+const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+
+/// Why an encrypted message cannot be opened. Retrying does not help, so the
+/// consumer reports these as poison instead of as handler failures.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecryptionError {
+    /// Encrypted message, but no `[encryption]` configured on this side
+    NotConfigured { key_id: String },
+    /// Sealed under a key this consumer does not have
+    UnknownKey { key_id: String },
+    /// Missing or malformed nonce, data key or body encoding
+    Malformed(String),
+    /// Authentication failed: body or attributes were modified, or the key is wrong
+    Tampered,
+}
+
+impl fmt::Display for DecryptionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotConfigured { key_id } => write!(
+                f,
+                "message is encrypted under {:?} but [encryption] is not configured",
+                key_id
+            ),
+            Self::UnknownKey { key_id } => write!(f, "unknown encryption key {:?}", key_id),
+            Self::Malformed(reason) => write!(f, "malformed encrypted message: {}", reason),
+            Self::Tampered => write!(
+                f,
+                "decryption failed: message was tampered with or sealed with another key"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DecryptionError {}
+
+/// The data key used for sealing; `encrypted` is what receivers hand to KMS.
+struct DataKey {
+    key_id: String,
+    key: Key<Aes256Gcm>,
+    encrypted: Option<Vec<u8>>,
+}
+
+enum KeySource {
+    Local(Key<Aes256Gcm>),
+    Kms(kms::Client),
+}
+
+pub struct Envelope {
+    /// Configured key id (local label, or KMS id/ARN/alias)
+    key_id: String,
+    source: KeySource,
+    /// KMS: one data key per process, generated on first use
+    sealing: OnceCell<DataKey>,
+    /// KMS: plaintext data keys by encrypted blob, so each is decrypted once
+    opened: Mutex<HashMap<Vec<u8>, Key<Aes256Gcm>>>,
+}
+
+pub async fn build_kms_client(cfg: &AppConfig) -> Result<kms::Client> {
+    let shared_cfg = load_sdk_config(cfg, cfg.encryption.endpoint_url.as_deref()).await;
+
+    let mut b = kms::config::Builder::from(&shared_cfg);
+    if let Some(ep) = &cfg.encryption.endpoint_url {
+        b = b.endpoint_url(ep.clone());
+    }
+    Ok(kms::Client::from_conf(b.build()))
+}
+
+/// Read a key file holding either 32 raw bytes or their base64 encoding.
+fn read_key_file(path: &str) -> Result<Key<Aes256Gcm>> {
+    let raw = std::fs::read(path).with_context(|| format!("reading key file {}", path))?;
+    let bytes = if raw.len() == KEY_LEN {
+        raw
+    } else {
+        let text = String::from_utf8_lossy(&raw);
+        BASE64
+            .decode(text.trim())
+            .with_context(|| format!("key file {} is neither 32 raw bytes nor base64", path))?
+    };
+    if bytes.len() != KEY_LEN {
+        return Err(anyhow!(
+            "key file {} holds {} bytes, AES-256 needs {}",
+            path,
+            bytes.len(),
+            KEY_LEN
+        ));
+    }
+    Ok(*Key::<Aes256Gcm>::from_slice(&bytes))
+}
+
+/// Associated data: version, key id and content-encoding (empty when the body
+/// is not compressed), NUL-separated.
+fn aad(version: &str, key_id: &str, content_encoding: Option<&str>) -> Vec<u8> {
+    [version, key_id, content_encoding.unwrap_or_default()]
+        .join("\0")
+        .into_bytes()
+}
+
+fn string_attr(value: &str) -> Result<MessageAttributeValue> {
+    Ok(MessageAttributeValue::builder()
+        .data_type("String")
+        .string_value(value)
+        .build()?)
+}
+
+fn binary_attr(bytes: &[u8]) -> Result<MessageAttributeValue> {
+    Ok(MessageAttributeValue::builder()
+        .data_type("Binary")
+        .binary_value(Blob::new(bytes))
+        .build()?)
+}
+
+/// The `encryption-key-id` of `m`, if it is encrypted.
+pub fn key_id(m: &Message) -> Option<&str> {
+    m.message_attributes()?.get(KEY_ID_ATTR)?.string_value()
+}
+
+impl Envelope {
+    /// Resolve `[encryption]`; `None` unless a mode is configured.
+    pub async fn from_config(cfg: &AppConfig) -> Result<Option<Self>> {
+        let enc = &cfg.encryption;
+        let Some(mode) = enc.mode else {
+            return Ok(None);
+        };
+        let (key_id, source) = match mode {
+            EncryptionMode::Local => {
+                let path = enc
+                    .key_file
+                    .as_deref()
+                    .ok_or_else(|| anyhow!("[encryption] mode = \"local\" requires key_file"))?;
+                let key_id = enc.key_id.clone().unwrap_or_else(|| "local".into());
+                (key_id, KeySource::Local(read_key_file(path)?))
+            }
+            EncryptionMode::Kms => {
+                let key_id = enc
+                    .key_id
+                    .clone()
+                    .ok_or_else(|| anyhow!("[encryption] mode = \"kms\" requires key_id"))?;
+                (key_id, KeySource::Kms(build_kms_client(cfg).await?))
+            }
+        };
+        Ok(Some(Self {
+            key_id,
+            source,
+            sealing: OnceCell::new(),
+            opened: Mutex::new(HashMap::new()),
+        }))
+    }
+
+    pub fn mode(&self) -> EncryptionMode {
+        match self.source {
+            KeySource::Local(_) => EncryptionMode::Local,
+            KeySource::Kms(_) => EncryptionMode::Kms,
+        }
+    }
+
+    pub fn key_id(&self) -> &str {
+        &self.key_id
+    }
+
+    async fn data_key(&self) -> Result<&DataKey> {
+        self.sealing
+            .get_or_try_init(|| async {
+                match &self.source {
+                    KeySource::Local(key) => Ok(DataKey {
+                        key_id: self.key_id.clone(),
+                        key: *key,
+                        encrypted: None,
+                    }),
+                    KeySource::Kms(client) => {
+                        let out = client
+                            .generate_data_key()
+                            .key_id(&self.key_id)
+                            .key_spec(DataKeySpec::Aes256)
+                            .send()
+                            .await
+                            .with_context(|| format!("GenerateDataKey under {}", self.key_id))?;
+                        let plaintext = out
+                            .plaintext()
+                            .ok_or_else(|| anyhow!("GenerateDataKey returned no plaintext"))?;
+                        let encrypted = out
+                            .ciphertext_blob()
+                            .ok_or_else(|| anyhow!("GenerateDataKey returned no ciphertext"))?;
+                        let key_id = out.key_id().unwrap_or(&self.key_id).to_string();
+                        info!("generated data key under {}", key_id);
+                        Ok(DataKey {
+                            key_id,
+                            key: *Key::<Aes256Gcm>::from_slice(plaintext.as_ref()),
+                            encrypted: Some(encrypted.as_ref().to_vec()),
+                        })
+                    }
+                }
+            })
+            .await
+    }
+
+    /// Encrypt the body (base64 of the ciphertext) and add the version, key id,
+    /// nonce and data key attributes. Compress first: the `content-encoding`
+    /// attribute is authenticated along with the body.
+    pub async fn seal(&self, mut entry: BatchEntry) -> Result<BatchEntry> {
+        let dk = self.data_key().await?;
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let content_encoding = entry
+            .attributes
+            .get(CONTENT_ENCODING_ATTR)
+            .and_then(|v| v.string_value());
+        let ciphertext = Aes256Gcm::new(&dk.key)
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: entry.body.as_bytes(),
+                    aad: &aad(ENVELOPE_VERSION, &dk.key_id, content_encoding),
+                },
+            )
+            .map_err(|_| anyhow!("AES-GCM encryption failed"))?;
+
+        entry.body = BASE64.encode(ciphertext);
+        entry
+            .attributes
+            .insert(VERSION_ATTR.to_string(), string_attr(ENVELOPE_VERSION)?);
+        entry
+            .attributes
+            .insert(KEY_ID_ATTR.to_string(), string_attr(&dk.key_id)?);
+        entry
+            .attributes
+            .insert(NONCE_ATTR.to_string(), binary_attr(&nonce)?);
+        if let Some(encrypted) = &dk.encrypted {
+            entry
+                .attributes
+                .insert(DATA_KEY_ATTR.to_string(), binary_attr(encrypted)?);
+        }
+        Ok(entry)
+    }
+
+    /// The AES key `m` was sealed with.
+    async fn opening_key(&self, m: &Message, key_id: &str) -> Result<Key<Aes256Gcm>> {
+        let client = match &self.source {
+            KeySource::Local(key) if key_id == self.key_id => return Ok(*key),
+            KeySource::Local(_) => {
+                return Err(DecryptionError::UnknownKey {
+                    key_id: key_id.to_string(),
+                }
+                .into());
+            }
+            KeySource::Kms(client) => client,
+        };
+
+        let blob = binary(m, DATA_KEY_ATTR)?.to_vec();
+        if let Some(key) = self
+            .opened
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&blob)
+        {
+            return Ok(*key);
+        }
+        let out = match client
+            .decrypt()
+            .ciphertext_blob(kms::primitives::Blob::new(blob.clone()))
+            .key_id(key_id)
+            .send()
+            .await
+        {
+            Ok(out) => out,
+            Err(e) => {
+                let poison = e.as_service_error().is_some_and(|se| {
+                    matches!(
+                        se,
+                        DecryptError::InvalidCiphertextException(_)
+                            | DecryptError::IncorrectKeyException(_)
+                            | DecryptError::NotFoundException(_)
+                    )
+                });
+                if poison {
+                    return Err(DecryptionError::UnknownKey {
+                        key_id: key_id.to_string(),
+                    }
+                    .into());
+                }
+                return Err(e).with_context(|| format!("KMS Decrypt under {}", key_id));
+            }
+        };
+        let plaintext = out
+            .plaintext()
+            .filter(|p| p.as_ref().len() == KEY_LEN)
+            .ok_or_else(|| DecryptionError::Malformed("KMS returned no AES-256 key".into()))?;
+        let key = *Key::<Aes256Gcm>::from_slice(plaintext.as_ref());
+        self.opened
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(blob, key);
+        Ok(key)
+    }
+
+    /// Decrypt `m` into a copy with the plaintext body and without the encryption
+    /// attributes.
+    async fn open(&self, m: &Message, key_id: &str) -> Result<Message> {
+        let attr = |name: &str| {
+            m.message_attributes()
+                .and_then(|a| a.get(name))
+                .and_then(|v| v.string_value())
+        };
+        let version = attr(VERSION_ATTR).ok_or_else(|| {
+            DecryptionError::Malformed(format!("missing {} attribute", VERSION_ATTR))
+        })?;
+        if version != ENVELOPE_VERSION {
+            return Err(DecryptionError::Malformed(format!(
+                "unsupported envelope version {:?}, expected {:?}",
+                version, ENVELOPE_VERSION
+            ))
+            .into());
+        }
+        let key = self.opening_key(m, key_id).await?;
+        let nonce = binary(m, NONCE_ATTR)?;
+        if nonce.len() != NONCE_LEN {
+            return Err(DecryptionError::Malformed(format!(
+                "{} is {} bytes, expected {}",
+                NONCE_ATTR,
+                nonce.len(),
+                NONCE_LEN
+            ))
+            .into());
+        }
+        let ciphertext = BASE64
+            .decode(m.body().unwrap_or_default())
+            .map_err(|_| DecryptionError::Malformed("body is not valid base64".into()))?;
+        let plaintext = Aes256Gcm::new(&key)
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: &ciphertext,
+                    aad: &aad(version, key_id, attr(CONTENT_ENCODING_ATTR)),
+                },
+            )
+            .map_err(|_| DecryptionError::Tampered)?;
+        let body = String::from_utf8(plaintext)
+            .map_err(|_| DecryptionError::Malformed("plaintext is not valid UTF-8".into()))?;
+
+        let mut plain = m.clone();
+        plain.body = Some(body);
+        if let Some(attrs) = plain.message_attributes.as_mut() {
+            for name in [VERSION_ATTR, KEY_ID_ATTR, NONCE_ATTR, DATA_KEY_ATTR] {
+                attrs.remove(name);
+            }
+        }
+        Ok(plain)
+    }
+}
+
+fn binary<'a>(m: &'a Message, name: &str) -> Result<&'a [u8], DecryptionError> {
+    m.message_attributes()
+        .and_then(|a| a.get(name))
+        .and_then(|v| v.binary_value())
+        .map(|b| b.as_ref())
+        .ok_or_else(|| DecryptionError::Malformed(format!("missing {} attribute", name)))
+}
+
+/// For an encrypted message, a decrypted copy; `None` for plain messages.
+/// Messages that cannot be opened fail with a [`DecryptionError`].
+pub async fn open(envelope: Option<&Envelope>, m: &Message) -> Result<Option<Message>> {
+    let Some(key_id) = key_id(m) else {
+        return Ok(None);
+    };
+    match envelope {
+        Some(env) => env.open(m, key_id).await.map(Some),
+        None => Err(DecryptionError::NotConfigured {
+            key_id: key_id.to_string(),
+        }
+        .into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local(key_id: &str, byte: u8) -> Envelope {
+        Envelope {
+            key_id: key_id.to_string(),
+            source: KeySource::Local(*Key::<Aes256Gcm>::from_slice(&[byte; KEY_LEN])),
+            sealing: OnceCell::new(),
+            opened: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// What a consumer receives for `entry`.
+    fn received(entry: &BatchEntry) -> Message {
+        Message::builder()
+            .message_id("m1")
+            .body(&entry.body)
+            .set_message_attributes(Some(entry.attributes.clone()))
+            .build()
+    }
+
+    async fn sealed(env: &Envelope, content_encoding: Option<&str>) -> Message {
+        let mut entry = BatchEntry {
+            body: "secret order".to_string(),
+            ..Default::default()
+        };
+        entry
+            .attributes
+            .insert("tenant".to_string(), string_attr("acme").unwrap());
+        if let Some(ce) = content_encoding {
+            entry
+                .attributes
+                .insert(CONTENT_ENCODING_ATTR.to_string(), string_attr(ce).unwrap());
+        }
+        received(&env.seal(entry).await.unwrap())
+    }
+
+    fn set_attr(m: &mut Message, name: &str, value: &str) {
+        m.message_attributes
+            .get_or_insert_default()
+            .insert(name.to_string(), string_attr(value).unwrap());
+    }
+
+    async fn open_err(env: Option<&Envelope>, m: &Message) -> DecryptionError {
+        open(env, m)
+            .await
+            .unwrap_err()
+            .downcast::<DecryptionError>()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn round_trip() {
+        let env = local("k1", 7);
+        let m = sealed(&env, None).await;
+        assert_ne!(m.body(), Some("secret order"));
+        assert_eq!(key_id(&m), Some("k1"));
+
+        let plain = open(Some(&env), &m).await.unwrap().unwrap();
+        assert_eq!(plain.body(), Some("secret order"));
+        let attrs = plain.message_attributes().unwrap();
+        assert_eq!(attrs.keys().collect::<Vec<_>>(), ["tenant"]);
+    }
+
+    #[tokio::test]
+    async fn plain_message_passes_through() {
+        let m = Message::builder().body("hello").build();
+        assert!(open(None, &m).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn tampered_ciphertext() {
+        let env = local("k1", 7);
+        let mut m = sealed(&env, None).await;
+        let mut raw = BASE64.decode(m.body().unwrap()).unwrap();
+        raw[0] ^= 1;
+        m.body = Some(BASE64.encode(raw));
+        assert_eq!(open_err(Some(&env), &m).await, DecryptionError::Tampered);
+    }
+
+    #[tokio::test]
+    async fn content_encoding_is_authenticated() {
+        let env = local("k1", 7);
+        let mut m = sealed(&env, Some("gzip")).await;
+        assert!(open(Some(&env), &m).await.is_ok());
+
+        set_attr(&mut m, CONTENT_ENCODING_ATTR, "zstd");
+        assert_eq!(open_err(Some(&env), &m).await, DecryptionError::Tampered);
+
+        m.message_attributes
+            .as_mut()
+            .unwrap()
+            .remove(CONTENT_ENCODING_ATTR);
+        assert_eq!(open_err(Some(&env), &m).await, DecryptionError::Tampered);
+    }
+
+    #[tokio::test]
+    async fn wrong_key_id() {
+        let env = local("k1", 7);
+        let mut m = sealed(&env, None).await;
+
+        // Another consumer's key
+        let other = local("k2", 9);
+        assert_eq!(
+            open_err(Some(&other), &m).await,
+            DecryptionError::UnknownKey {
+                key_id: "k1".to_string()
+            }
+        );
+        // Same label, different key material
+        assert_eq!(
+            open_err(Some(&local("k1", 9)), &m).await,
+            DecryptionError::Tampered
+        );
+        // Relabelled message
+        set_attr(&mut m, KEY_ID_ATTR, "k2");
+        assert_eq!(open_err(Some(&other), &m).await, DecryptionError::Tampered);
+        assert_eq!(
+            open_err(None, &m).await,
+            DecryptionError::NotConfigured {
+                key_id: "k2".to_string()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn envelope_version() {
+        let env = local("k1", 7);
+        let mut m = sealed(&env, None).await;
+        set_attr(&mut m, VERSION_ATTR, "2");
+        assert!(matches!(
+            open_err(Some(&env), &m).await,
+            DecryptionError::Malformed(_)
+        ));
+
+        m.message_attributes.as_mut().unwrap().remove(VERSION_ATTR);
+        assert!(matches!(
+            open_err(Some(&env), &m).await,
+            DecryptionError::Malformed(_)
+        ));
+    }
+}
@@ -0,0 +1,56 @@
+//! FIFO helpers: system-attribute accessors and a per-group order verifier.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use aws_sdk_sqs::types::{Message, MessageSystemAttributeName};
+
+pub fn group_id(m: &Message) -> Option<&str> {
+    m.attributes()
+        .and_then(|a| a.get(&MessageSystemAttributeName::MessageGroupId))
+        .map(String::as_str)
+}
+
+/// FIFO SequenceNumber (up to 128 bits, sent as a decimal string).
+pub fn sequence_number(m: &Message) -> Option<u128> {
+    m.attributes()
+        .and_then(|a| a.get(&MessageSystemAttributeName::SequenceNumber))
+        .and_then(|s| s.parse().ok())
+}
+
+/// How a message's SequenceNumber relates to the last one seen in its group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Order {
+    /// First message of the group, or strictly after the previous one
+    InOrder,
+    /// Same SequenceNumber as the previous one (retry/redelivery)
+    Redelivered,
+    /// Lower than a SequenceNumber already processed in this group
+    OutOfOrder { previous: u128 },
+    /// Not a FIFO message (no group or sequence number)
+    Unordered,
+}
+
+/// Tracks the highest SequenceNumber processed per MessageGroupId.
+#[derive(Debug, Default)]
+pub struct OrderVerifier {
+    last: Mutex<HashMap<String, u128>>,
+}
+
+impl OrderVerifier {
+    /// Record `m` as being processed now and classify it against its group.
+    pub fn observe(&self, m: &Message) -> Order {
+        let (Some(group), Some(seq)) = (group_id(m), sequence_number(m)) else {
+            return Order::Unordered;
+        };
+        let mut last = self.last.lock().unwrap_or_else(|e| e.into_inner());
+        match last.get(group).copied() {
+            Some(prev) if seq < prev => Order::OutOfOrder { previous: prev },
+            Some(prev) if seq == prev => Order::Redelivered,
+            _ => {
+                last.insert(group.to_string(), seq);
+                Order::InOrder
+            }
+        }
+    }
+}
@@ -0,0 +1,419 @@
+//! Local evaluator for SNS subscription filter policies.
+//! Covers the operators commonly used in labs: exact string/number match,
+//! `prefix`, `suffix`, `equals-ignore-case`, `anything-but`, `numeric`,
+//! `exists` and `$or`. Anything else is reported as unsupported.
+
+use std::collections::BTreeMap;
+
+use anyhow::{Context, Result, anyhow, bail};
+use serde_json::{Map, Value};
+
+use crate::config::FilterPolicyScope;
+
+/// Convert an SNS/SQS attribute (data type + raw value) into the values a policy is matched against.
+/// `String.Array` attributes expand to their elements.
+pub fn attribute_values(data_type: &str, raw: &str) -> Result<Vec<Value>> {
+    match data_type.split('.').next().unwrap_or(data_type) {
+        "Number" => {
+            let n: f64 = raw
+                .parse()
+                .with_context(|| format!("attribute value {raw:?} is not a Number"))?;
+            Ok(vec![Value::from(n)])
+        }
+        "String" if data_type == "String.Array" => match serde_json::from_str(raw) {
+            Ok(Value::Array(items)) => Ok(items),
+            _ => Err(anyhow!(
+                "String.Array value must be a JSON array (got {raw:?})"
+            )),
+        },
+        "String" => Ok(vec![Value::String(raw.to_string())]),
+        // Binary attributes are never matched by SNS filter policies
+        _ => Ok(Vec::new()),
+    }
+}
+
+/// Evaluate `policy` against either the message attributes or the JSON body, depending on `scope`.
+pub fn matches(
+    policy: &Value,
+    scope: FilterPolicyScope,
+    attrs: &BTreeMap<String, Vec<Value>>,
+    body: &str,
+) -> Result<bool> {
+    let policy = policy
+        .as_object()
+        .ok_or_else(|| anyhow!("filter policy must be a JSON object"))?;
+
+    match scope {
+        FilterPolicyScope::MessageAttributes => eval_attrs(policy, attrs),
+        FilterPolicyScope::MessageBody => {
+            let body: Value = serde_json::from_str(body)
+                .context("MessageBody scope requires a JSON message body")?;
+            eval_body(policy, &body)
+        }
+    }
+}
+
+fn or_branches(cond: &Value) -> Result<&Vec<Value>> {
+    cond.as_array()
+        .ok_or_else(|| anyhow!("$or must be an array of policies"))
+}
+
+fn eval_attrs(policy: &Map<String, Value>, attrs: &BTreeMap<String, Vec<Value>>) -> Result<bool> {
+    for (key, cond) in policy {
+        let ok = if key == "$or" {
+            let mut any = false;
+            for branch in or_branches(cond)? {
+                let branch = branch
+                    .as_object()
+                    .ok_or_else(|| anyhow!("$or entries must be objects"))?;
+                any |= eval_attrs(branch, attrs)?;
+            }
+            any
+        } else {
+            let conds = cond
+                .as_array()
+                .ok_or_else(|| anyhow!("conditions for {key:?} must be an array"))?;
+            any_condition(conds, attrs.get(key).map(Vec::as_slice))?
+        };
+        if !ok {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+fn eval_body(policy: &Map<String, Value>, body: &Value) -> Result<bool> {
+    for (key, cond) in policy {
+        let ok = if key == "$or" {
+            let mut any = false;
+            for branch in or_branches(cond)? {
+                let branch = branch
+                    .as_object()
+                    .ok_or_else(|| anyhow!("$or entries must be objects"))?;
+                any |= eval_body(branch, body)?;
+            }
+            any
+        } else {
+            let field = body.get(key);
+            match cond {
+                // Nested object: descend into the matching body field
+                Value::Object(nested) => match field {
+                    Some(inner @ Value::Object(_)) => eval_body(nested, inner)?,
+                    _ => false,
+                },
+                Value::Array(conds) => {
+                    let values = match field {
+                        Some(Value::Array(items)) => Some(items.clone()),
+                        Some(v) => Some(vec![v.clone()]),
+                        None => None,
+                    };
+                    any_condition(conds, values.as_deref())?
+                }
+                _ => bail!("conditions for {key:?} must be an array or nested object"),
+            }
+        };
+        if !ok {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+fn any_condition(conds: &[Value], values: Option<&[Value]>) -> Result<bool> {
+    for c in conds {
+        if condition_matches(c, values)? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+fn condition_matches(cond: &Value, values: Option<&[Value]>) -> Result<bool> {
+    let Value::Object(op) = cond else {
+        // Literal: exact match against any value
+        return Ok(values.is_some_and(|vs| vs.iter().any(|v| literal_eq(cond, v))));
+    };
+
+    let (name, arg) = op
+        .iter()
+        .next()
+        .filter(|_| op.len() == 1)
+        .ok_or_else(|| anyhow!("operator objects must have exactly one key (got {cond})"))?;
+
+    if name == "exists" {
+        let want = arg
+            .as_bool()
+            .ok_or_else(|| anyhow!("exists expects true or false"))?;
+        return Ok(values.is_some() == want);
+    }
+
+    let Some(values) = values else {
+        return Ok(false);
+    };
+
+    match name.as_str() {
+        "prefix" => {
+            let p = expect_str(name, arg)?;
+            Ok(strings(values).any(|s| s.starts_with(p)))
+        }
+        "suffix" => {
+            let p = expect_str(name, arg)?;
+            Ok(strings(values).any(|s| s.ends_with(p)))
+        }
+        "equals-ignore-case" => {
+            let p = expect_str(name, arg)?;
+            Ok(strings(values).any(|s| s.eq_ignore_ascii_case(p)))
+        }
+        "anything-but" => {
+            for v in values {
+                if !anything_but_excludes(arg, v)? {
+                    return Ok(true);
+                }
+            }
+            Ok(false)
+        }
+        "numeric" => {
+            let spec = arg
+                .as_array()
+                .ok_or_else(|| anyhow!("numeric expects an array like [\">\", 0, \"<=\", 5]"))?;
+            if spec.is_empty() || spec.len() % 2 != 0 {
+                bail!("numeric expects operator/value pairs (got {arg})");
+            }
+            let mut checks = Vec::new();
+            for pair in spec.chunks(2) {
+                let op = expect_str("numeric", &pair[0])?;
+                let n = pair[1]
+                    .as_f64()
+                    .ok_or_else(|| anyhow!("numeric bound must be a number (got {})", pair[1]))?;
+                checks.push((op, n));
+            }
+            for v in values.iter().filter_map(Value::as_f64) {
+                let mut all = true;
+                for (op, n) in &checks {
+                    all &= match *op {
+                        "=" => v == *n,
+                        ">" => v > *n,
+                        ">=" => v >= *n,
+                        "<" => v < *n,
+                        "<=" => v <= *n,
+                        other => bail!("unsupported numeric operator {other:?}"),
+                    };
+                }
+                if all {
+                    return Ok(true);
+                }
+            }
+            Ok(false)
+        }
+        other => bail!("unsupported filter operator {other:?}"),
+    }
+}
+
+fn anything_but_excludes(arg: &Value, v: &Value) -> Result<bool> {
+    match arg {
+        Value::Array(list) => Ok(list.iter().any(|l| literal_eq(l, v))),
+        Value::Object(inner) => match inner.get("prefix") {
+            Some(p) => {
+                let p = expect_str("prefix", p)?;
+                Ok(v.as_str().is_some_and(|s| s.starts_with(p)))
+            }
+            None => bail!("anything-but only supports a nested prefix operator"),
+        },
+        literal => Ok(literal_eq(literal, v)),
+    }
+}
+
+fn expect_str<'a>(op: &str, v: &'a Value) -> Result<&'a str> {
+    v.as_str()
+        .ok_or_else(|| anyhow!("{op} expects a string (got {v})"))
+}
+
+fn strings(values: &[Value]) -> impl Iterator<Item = &str> {
+    values.iter().filter_map(Value::as_str)
+}
+
+fn literal_eq(literal: &Value, v: &Value) -> bool {
+    match (literal, v) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64() == b.as_f64(),
+        (a, b) => a == b,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn attrs(pairs: &[(&str, &str, &str)]) -> BTreeMap<String, Vec<Value>> {
+        pairs
+            .iter()
+            .map(|(name, data_type, raw)| {
+                (name.to_string(), attribute_values(data_type, raw).unwrap())
+            })
+            .collect()
+    }
+
+    fn check(cases: &[(&str, Value, bool)], attrs: &BTreeMap<String, Vec<Value>>) {
+        for (name, policy, want) in cases {
+            let got = matches(policy, FilterPolicyScope::MessageAttributes, attrs, "").unwrap();
+            assert_eq!(got, *want, "{name}: {policy}");
+        }
+    }
+
+    #[test]
+    fn operators() {
+        let a = attrs(&[
+            ("event_type", "String", "user.created"),
+            ("price", "Number", "42.5"),
+            ("tags", "String.Array", r#"["red", "blue"]"#),
+        ]);
+        check(
+            &[
+                ("exact", json!({"event_type": ["user.created"]}), true),
+                ("exact miss", json!({"event_type": ["user.deleted"]}), false),
+                ("array element", json!({"tags": ["blue"]}), true),
+                ("prefix", json!({"event_type": [{"prefix": "user."}]}), true),
+                (
+                    "prefix miss",
+                    json!({"event_type": [{"prefix": "order."}]}),
+                    false,
+                ),
+                ("numeric exact", json!({"price": [42.5]}), true),
+                (
+                    "numeric range",
+                    json!({"price": [{"numeric": [">", 40, "<=", 50]}]}),
+                    true,
+                ),
+                (
+                    "numeric below",
+                    json!({"price": [{"numeric": ["<", 42.5]}]}),
+                    false,
+                ),
+                (
+                    "numeric equal",
+                    json!({"price": [{"numeric": ["=", 42.5]}]}),
+                    true,
+                ),
+                (
+                    "anything-but",
+                    json!({"event_type": [{"anything-but": ["user.deleted"]}]}),
+                    true,
+                ),
+                (
+                    "anything-but hit",
+                    json!({"event_type": [{"anything-but": ["user.created"]}]}),
+                    false,
+                ),
+                (
+                    "anything-but prefix",
+                    json!({"event_type": [{"anything-but": {"prefix": "user."}}]}),
+                    false,
+                ),
+                ("exists", json!({"price": [{"exists": true}]}), true),
+                (
+                    "exists missing",
+                    json!({"tenant": [{"exists": true}]}),
+                    false,
+                ),
+                ("not exists", json!({"tenant": [{"exists": false}]}), true),
+                ("missing key", json!({"tenant": ["acme"]}), false),
+            ],
+            &a,
+        );
+    }
+
+    #[test]
+    fn and_across_keys_or_across_values() {
+        let a = attrs(&[
+            ("event_type", "String", "order.paid"),
+            ("tenant", "String", "acme"),
+        ]);
+        check(
+            &[
+                (
+                    "both keys",
+                    json!({"event_type": ["order.paid"], "tenant": ["acme"]}),
+                    true,
+                ),
+                (
+                    "one key fails",
+                    json!({"event_type": ["order.paid"], "tenant": ["globex"]}),
+                    false,
+                ),
+                ("any value", json!({"tenant": ["globex", "acme"]}), true),
+                ("no value", json!({"tenant": ["globex", "initech"]}), false),
+                (
+                    "$or",
+                    json!({"$or": [{"tenant": ["globex"]}, {"event_type": [{"prefix": "order."}]}]}),
+                    true,
+                ),
+                (
+                    "$or none",
+                    json!({"$or": [{"tenant": ["globex"]}, {"event_type": ["user.created"]}]}),
+                    false,
+                ),
+            ],
+            &a,
+        );
+    }
+
+    #[test]
+    fn message_body_scope() {
+        let body = r#"{"order": {"status": "paid", "total": 120, "customer": {"tier": "gold"}}, "source": "web"}"#;
+        let cases = [
+            ("top level", json!({"source": ["web"]}), true),
+            ("nested", json!({"order": {"status": ["paid"]}}), true),
+            (
+                "deeply nested",
+                json!({"order": {"customer": {"tier": [{"prefix": "go"}]}}}),
+                true,
+            ),
+            (
+                "nested numeric",
+                json!({"order": {"total": [{"numeric": [">=", 100]}]}}),
+                true,
+            ),
+            (
+                "nested miss",
+                json!({"order": {"status": ["refunded"]}}),
+                false,
+            ),
+            (
+                "and across levels",
+                json!({"source": ["web"], "order": {"status": ["open"]}}),
+                false,
+            ),
+            ("not an object", json!({"source": {"kind": ["web"]}}), false),
+            (
+                "missing nested",
+                json!({"order": {"coupon": [{"exists": false}]}}),
+                true,
+            ),
+        ];
+        for (name, policy, want) in cases {
+            let got = matches(
+                &policy,
+                FilterPolicyScope::MessageBody,
+                &BTreeMap::new(),
+                body,
+            )
+            .unwrap();
+            assert_eq!(got, want, "{name}: {policy}");
+        }
+    }
+
+    #[test]
+    fn body_scope_requires_json() {
+        let policy = json!({"source": ["web"]});
+        assert!(
+            matches(
+                &policy,
+                FilterPolicyScope::MessageBody,
+                &BTreeMap::new(),
+                "plain text"
+            )
+            .is_err()
+        );
+    }
+}
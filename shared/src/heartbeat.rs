@@ -0,0 +1,106 @@
+//! Visibility-timeout heartbeat: keeps in-flight messages invisible while a
+//! long-running handler is still working on them.
+
+use std::future::Future;
+use std::time::Duration;
+
+use anyhow::{Result, bail};
+use aws_sdk_sqs::Client;
+use tokio::time::{Instant, interval_at};
+use tracing::{info, warn};
+
+use crate::config::AppConfig;
+use crate::sqs;
+
+/// SQS caps a message's total visibility at 12 hours.
+const MAX_VISIBILITY_SECS: i32 = 43_200;
+
+#[derive(Debug, Clone)]
+pub struct Heartbeat {
+    pub interval: Duration,
+    pub extend_secs: i32,
+    pub max_total: Duration,
+}
+
+impl Heartbeat {
+    /// Resolve `[recv.heartbeat]`, falling back to the queue's visibility timeout.
+    /// Rejects settings under which a beat could never keep a message invisible:
+    /// an interval that is not shorter than the extension, or an extension longer
+    /// than `max_secs` (the first beat would already give up).
+    pub fn from_config(cfg: &AppConfig) -> Result<Self> {
+        let hb = &cfg.recv.heartbeat;
+        let extend_secs = hb
+            .extend_secs
+            .or(cfg.sqs.visibility_timeout_secs)
+            .unwrap_or(30)
+            .clamp(1, MAX_VISIBILITY_SECS);
+        let interval_secs = hb.interval_secs.unwrap_or((extend_secs as u64 / 2).max(1));
+        let max_secs = hb.max_secs.unwrap_or(300);
+
+        if interval_secs == 0 || interval_secs >= extend_secs as u64 {
+            bail!(
+                "[recv.heartbeat] interval_secs ({}) must be between 1 and extend_secs ({}) exclusive, \
+                 or messages become visible between beats",
+                interval_secs,
+                extend_secs
+            );
+        }
+        if extend_secs as u64 > max_secs {
+            bail!(
+                "[recv.heartbeat] extend_secs ({}) must not exceed max_secs ({})",
+                extend_secs,
+                max_secs
+            );
+        }
+
+        Ok(Self {
+            interval: Duration::from_secs(interval_secs),
+            extend_secs,
+            max_total: Duration::from_secs(max_secs),
+        })
+    }
+
+    /// Run `work` while periodically extending the visibility of `receipt_handles`.
+    /// Beats stop when `work` completes, or once the next extension would pass `max_total`;
+    /// after that the messages become visible again when their timeout lapses.
+    pub async fn run<F: Future>(
+        &self,
+        client: &Client,
+        queue_url: &str,
+        receipt_handles: &[String],
+        work: F,
+    ) -> F::Output {
+        tokio::pin!(work);
+        let start = Instant::now();
+        let mut ticker = interval_at(start + self.interval, self.interval);
+        let mut active = !receipt_handles.is_empty();
+
+        loop {
+            tokio::select! {
+                out = &mut work => return out,
+                _ = ticker.tick(), if active => {
+                    let extend = Duration::from_secs(self.extend_secs as u64);
+                    if start.elapsed() + extend > self.max_total {
+                        warn!(
+                            "heartbeat giving up after {:?} (max {:?}); messages may be redelivered",
+                            start.elapsed(),
+                            self.max_total
+                        );
+                        active = false;
+                        continue;
+                    }
+                    for rh in receipt_handles {
+                        if let Err(e) = sqs::change_visibility(client, queue_url, rh, self.extend_secs).await {
+                            warn!("heartbeat failed to extend visibility: {e:#}");
+                        }
+                    }
+                    info!(
+                        "heartbeat: visibility extended to {}s for {} message(s)",
+                        self.extend_secs,
+                        receipt_handles.len()
+                    );
+                }
+            }
+        }
+    }
+}
@@ -1,4 +1,19 @@
+pub mod attrs;
+pub mod batch;
+pub mod codec;
 pub mod config;
+pub mod consumer;
+pub mod encryption;
+pub mod fifo;
+pub mod filter_policy;
+pub mod heartbeat;
 pub mod logging;
+pub mod offload;
+pub mod reconcile;
+pub mod retry;
+pub mod shutdown;
+pub mod sns;
 pub mod sqs;
-pub mod cli;
\ No newline at end of file
+pub mod typed;
+pub mod validate;
+pub mod cli;
@@ -0,0 +1,282 @@
+//! Large payload offload to S3 (extended client pattern): oversized bodies are
+//! uploaded to a bucket and replaced by a pointer message carrying a marker
+//! attribute; consumers fetch the payload back and delete the object once the
+//! message is acknowledged.
+//!
+//! The pointer format matches the AWS extended client libraries, so messages
+//! interoperate with the Java/Python implementations.
+
+use anyhow::{Context, Result, anyhow};
+use aws_sdk_s3 as s3;
+use aws_sdk_s3::error::SdkError;
+use aws_sdk_s3::operation::create_bucket::CreateBucketError;
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::types::{BucketLocationConstraint, CreateBucketConfiguration};
+use aws_sdk_sqs::types::{Message, MessageAttributeValue};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::attrs::MAX_ATTRIBUTES;
+use crate::batch::BatchEntry;
+use crate::config::{AppConfig, load_sdk_config};
+use crate::validate::MAX_MESSAGE_BYTES;
+
+/// Number attribute set on pointer messages: the original body size in bytes.
+pub const MARKER_ATTR: &str = "ExtendedPayloadSize";
+const POINTER_CLASS: &str = "software.amazon.payloadoffloading.PayloadS3Pointer";
+
+/// Location of an offloaded body.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct S3Pointer {
+    pub s3_bucket_name: String,
+    pub s3_key: String,
+}
+
+impl S3Pointer {
+    /// Pointer message body: `["...PayloadS3Pointer", {"s3BucketName": ..., "s3Key": ...}]`.
+    pub fn to_body(&self) -> String {
+        serde_json::json!([POINTER_CLASS, self]).to_string()
+    }
+
+    /// The pointer carried by `m`, if it has the marker attribute and a pointer body.
+    pub fn from_message(m: &Message) -> Option<Self> {
+        m.message_attributes()?.get(MARKER_ATTR)?;
+        let (class, ptr): (String, Self) = serde_json::from_str(m.body()?).ok()?;
+        (class == POINTER_CLASS).then_some(ptr)
+    }
+}
+
+impl std::fmt::Display for S3Pointer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "s3://{}/{}", self.s3_bucket_name, self.s3_key)
+    }
+}
+
+pub async fn build_s3_client(cfg: &AppConfig) -> Result<s3::Client> {
+    let shared_cfg = load_sdk_config(cfg, cfg.offload.endpoint_url.as_deref()).await;
+
+    let mut b = s3::config::Builder::from(&shared_cfg);
+    if let Some(ep) = &cfg.offload.endpoint_url {
+        // LocalStack serves buckets by path, not by virtual host
+        b = b.endpoint_url(ep.clone()).force_path_style(true);
+    }
+    Ok(s3::Client::from_conf(b.build()))
+}
+
+#[derive(Debug, Clone)]
+pub struct Offloader {
+    client: s3::Client,
+    pub bucket: String,
+    pub threshold_bytes: usize,
+    pub always: bool,
+    pub key_prefix: String,
+    region: String,
+}
+
+impl Offloader {
+    /// Resolve `[offload]`; `None` unless a bucket is configured.
+    pub async fn from_config(cfg: &AppConfig) -> Result<Option<Self>> {
+        let off = &cfg.offload;
+        let Some(bucket) = off.bucket.clone() else {
+            return Ok(None);
+        };
+        Ok(Some(Self {
+            client: build_s3_client(cfg).await?,
+            bucket,
+            threshold_bytes: off.threshold_bytes.unwrap_or(MAX_MESSAGE_BYTES),
+            always: off.always.unwrap_or(false),
+            key_prefix: off.key_prefix.clone().unwrap_or_else(|| "payloads/".into()),
+            region: cfg.runtime.region.clone(),
+        }))
+    }
+
+    pub fn should_offload(&self, entry: &BatchEntry) -> bool {
+        self.always || entry.size() > self.threshold_bytes
+    }
+
+    /// Upload the body if it should be offloaded and return the pointer entry;
+    /// other entries are returned unchanged.
+    pub async fn offload(&self, mut entry: BatchEntry) -> Result<BatchEntry> {
+        if !self.should_offload(&entry) {
+            return Ok(entry);
+        }
+        // The marker takes an attribute slot; fail before anything is uploaded
+        if entry.attributes.len() >= MAX_ATTRIBUTES && !entry.attributes.contains_key(MARKER_ATTR) {
+            return Err(anyhow!(
+                "cannot offload a message with {} attributes: {} needs a free slot",
+                entry.attributes.len(),
+                MARKER_ATTR
+            ));
+        }
+        let ptr = S3Pointer {
+            s3_bucket_name: self.bucket.clone(),
+            s3_key: format!(
+                "{}{:016x}{:016x}",
+                self.key_prefix,
+                fastrand::u64(..),
+                fastrand::u64(..)
+            ),
+        };
+        let size = entry.body.len();
+        self.client
+            .put_object()
+            .bucket(&ptr.s3_bucket_name)
+            .key(&ptr.s3_key)
+            .body(ByteStream::from(
+                std::mem::take(&mut entry.body).into_bytes(),
+            ))
+            .send()
+            .await
+            .with_context(|| format!("uploading payload to {ptr}"))?;
+        info!("offloaded {} bytes to {}", size, ptr);
+
+        entry.body = ptr.to_body();
+        entry.attributes.insert(
+            MARKER_ATTR.to_string(),
+            MessageAttributeValue::builder()
+                .data_type("Number")
+                .string_value(size.to_string())
+                .build()?,
+        );
+        Ok(entry)
+    }
+
+    pub async fn fetch(&self, ptr: &S3Pointer) -> Result<String> {
+        let out = self
+            .client
+            .get_object()
+            .bucket(&ptr.s3_bucket_name)
+            .key(&ptr.s3_key)
+            .send()
+            .await
+            .with_context(|| format!("fetching payload {ptr}"))?;
+        let bytes = out
+            .body
+            .collect()
+            .await
+            .with_context(|| format!("reading payload {ptr}"))?
+            .into_bytes();
+        String::from_utf8(bytes.to_vec()).map_err(|_| anyhow!("payload {ptr} is not valid UTF-8"))
+    }
+
+    pub async fn delete(&self, ptr: &S3Pointer) -> Result<()> {
+        self.client
+            .delete_object()
+            .bucket(&ptr.s3_bucket_name)
+            .key(&ptr.s3_key)
+            .send()
+            .await
+            .with_context(|| format!("deleting payload {ptr}"))?;
+        Ok(())
+    }
+
+    /// For a pointer message, a copy of `m` with the payload as body and without the
+    /// marker attribute, plus the pointer; `None` for regular messages.
+    pub async fn resolve(&self, m: &Message) -> Result<Option<(Message, S3Pointer)>> {
+        let Some(ptr) = S3Pointer::from_message(m) else {
+            return Ok(None);
+        };
+        let mut full = m.clone();
+        full.body = Some(self.fetch(&ptr).await?);
+        if let Some(attrs) = full.message_attributes.as_mut() {
+            attrs.remove(MARKER_ATTR);
+        }
+        Ok(Some((full, ptr)))
+    }
+
+    /// Create the bucket if it does not exist yet.
+    pub async fn ensure_bucket(&self) -> Result<()> {
+        let mut req = self.client.create_bucket().bucket(&self.bucket);
+        // us-east-1 is the default location and must not be sent as a constraint
+        if self.region != "us-east-1" {
+            req = req.create_bucket_configuration(
+                CreateBucketConfiguration::builder()
+                    .location_constraint(BucketLocationConstraint::from(self.region.as_str()))
+                    .build(),
+            );
+        }
+        match req.send().await {
+            Ok(_) => info!("Created bucket: {}", self.bucket),
+            Err(SdkError::ServiceError(e))
+                if matches!(e.err(), CreateBucketError::BucketAlreadyOwnedByYou(_)) =>
+            {
+                info!("Bucket already exists: {}", self.bucket)
+            }
+            Err(e) => {
+                return Err(e).with_context(|| format!("creating bucket {}", self.bucket));
+            }
+        }
+        Ok(())
+    }
+
+    /// Empty the bucket, then delete it.
+    pub async fn delete_bucket(&self) -> Result<()> {
+        let mut token: Option<String> = None;
+        loop {
+            let out = self
+                .client
+                .list_objects_v2()
+                .bucket(&self.bucket)
+                .set_continuation_token(token)
+                .send()
+                .await
+                .with_context(|| format!("listing bucket {}", self.bucket))?;
+            for obj in out.contents() {
+                if let Some(key) = obj.key() {
+                    self.client
+                        .delete_object()
+                        .bucket(&self.bucket)
+                        .key(key)
+                        .send()
+                        .await
+                        .with_context(|| format!("deleting s3://{}/{}", self.bucket, key))?;
+                }
+            }
+            token = out.next_continuation_token().map(str::to_string);
+            if token.is_none() {
+                break;
+            }
+        }
+        self.client
+            .delete_bucket()
+            .bucket(&self.bucket)
+            .send()
+            .await
+            .with_context(|| format!("deleting bucket {}", self.bucket))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn offload_needs_an_attribute_slot() {
+        let conf = s3::Config::builder().behavior_version_latest().build();
+        let off = Offloader {
+            client: s3::Client::from_conf(conf),
+            bucket: "bucket".to_string(),
+            threshold_bytes: 0,
+            always: true,
+            key_prefix: String::new(),
+            region: "us-east-1".to_string(),
+        };
+        let value = MessageAttributeValue::builder()
+            .data_type("String")
+            .string_value("v")
+            .build()
+            .unwrap();
+        let entry = BatchEntry {
+            body: "body".to_string(),
+            attributes: (0..MAX_ATTRIBUTES)
+                .map(|i| (format!("a{i}"), value.clone()))
+                .collect(),
+            ..Default::default()
+        };
+        // Rejected before the upload, which this client could not perform
+        let err = off.offload(entry).await.unwrap_err();
+        assert!(err.to_string().contains(MARKER_ATTR), "{err}");
+    }
+}
@@ -0,0 +1,309 @@
+//! Drift detection for existing queues: the attributes `[sqs]` asks for are
+//! compared with what `GetQueueAttributes` reports, printed as a plan and, on
+//! request, applied with `SetQueueAttributes`. Settings left out of the config
+//! are not managed and never show up as drift. Attributes that cannot change
+//! on a live queue (the FIFO flag) are reported but never applied.
+
+use std::collections::HashMap;
+
+use anyhow::{Context, Result};
+use aws_sdk_sqs::Client;
+use aws_sdk_sqs::types::QueueAttributeName;
+
+use crate::config::SqsConfig;
+use crate::sqs;
+
+/// Attributes that cannot be changed after the queue is created.
+const IMMUTABLE: &[QueueAttributeName] = &[QueueAttributeName::FifoQueue];
+
+/// One attribute whose live value differs from the config.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    pub attribute: QueueAttributeName,
+    /// Live value; `None` when the queue does not report the attribute
+    pub current: Option<String>,
+    pub desired: String,
+}
+
+impl Change {
+    /// Only fixable by deleting and recreating the queue.
+    pub fn requires_recreate(&self) -> bool {
+        IMMUTABLE.contains(&self.attribute)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Plan {
+    pub queue_name: String,
+    pub changes: Vec<Change>,
+}
+
+fn same_value(current: &str, desired: &str) -> bool {
+    current == desired || (is_bool(desired) && current.eq_ignore_ascii_case(desired))
+}
+
+fn is_bool(v: &str) -> bool {
+    v == "true" || v == "false"
+}
+
+/// Compare desired attributes (see [`sqs::queue_attributes`]) with live ones.
+pub fn diff(
+    desired: &[(QueueAttributeName, String)],
+    actual: &HashMap<QueueAttributeName, String>,
+) -> Vec<Change> {
+    let mut want: Vec<(QueueAttributeName, String)> = desired.to_vec();
+    let wants = |k: &QueueAttributeName| desired.iter().any(|(w, _)| w == k);
+    let live_kms = actual
+        .get(&QueueAttributeName::KmsMasterKeyId)
+        .is_some_and(|v| !v.is_empty());
+    let live_sse_sqs = actual
+        .get(&QueueAttributeName::SqsManagedSseEnabled)
+        .is_some_and(|v| v == "true");
+
+    // Standard queues do not set FifoQueue, and SQS omits it for them
+    if !wants(&QueueAttributeName::FifoQueue) {
+        want.push((QueueAttributeName::FifoQueue, "false".to_string()));
+    }
+    // Switching between SSE-SQS and SSE-KMS means turning the other one off
+    if wants(&QueueAttributeName::SqsManagedSseEnabled) && live_kms {
+        want.push((QueueAttributeName::KmsMasterKeyId, String::new()));
+    }
+    if wants(&QueueAttributeName::KmsMasterKeyId) && live_sse_sqs {
+        want.push((
+            QueueAttributeName::SqsManagedSseEnabled,
+            "false".to_string(),
+        ));
+    }
+
+    let actual_value = |k: &QueueAttributeName| -> Option<String> {
+        match actual.get(k) {
+            Some(v) => Some(v.clone()),
+            None if *k == QueueAttributeName::FifoQueue => Some("false".to_string()),
+            None => None,
+        }
+    };
+
+    want.into_iter()
+        .filter_map(|(attribute, desired)| {
+            let current = actual_value(&attribute);
+            match &current {
+                Some(c) if same_value(c, &desired) => None,
+                // Unset and empty mean the same (e.g. no KMS key)
+                None if desired.is_empty() => None,
+                _ => Some(Change {
+                    attribute,
+                    current,
+                    desired,
+                }),
+            }
+        })
+        .collect()
+}
+
+/// Build the plan for the existing queue at `queue_url`.
+pub async fn plan(
+    client: &Client,
+    queue_url: &str,
+    queue_name: &str,
+    sqs_cfg: &SqsConfig,
+) -> Result<Plan> {
+    let desired = sqs::queue_attributes(queue_name, sqs_cfg)?;
+    let out = client
+        .get_queue_attributes()
+        .queue_url(queue_url)
+        .attribute_names(QueueAttributeName::All)
+        .send()
+        .await
+        .with_context(|| format!("getting attributes of {queue_name}"))?;
+    let actual = out.attributes().cloned().unwrap_or_default();
+
+    Ok(Plan {
+        queue_name: queue_name.to_string(),
+        changes: diff(&desired, &actual),
+    })
+}
+
+impl Plan {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn needs_recreate(&self) -> bool {
+        self.changes.iter().any(Change::requires_recreate)
+    }
+
+    /// `~` marks an update, `+` an attribute the queue does not report yet,
+    /// `!` a difference only recreation can fix.
+    pub fn print(&self) {
+        if self.is_empty() {
+            println!("[plan] {}: up to date", self.queue_name);
+            return;
+        }
+        println!(
+            "[plan] {}: {} difference(s)",
+            self.queue_name,
+            self.changes.len()
+        );
+        for c in &self.changes {
+            let current = c.current.as_deref().unwrap_or("(unset)");
+            let desired = if c.desired.is_empty() {
+                "(unset)"
+            } else {
+                &c.desired
+            };
+            let (mark, note) = if c.requires_recreate() {
+                ("!", " (immutable: delete and recreate the queue)")
+            } else if c.current.is_none() {
+                ("+", "")
+            } else {
+                ("~", "")
+            };
+            println!(
+                "[plan]   {} {}: {} -> {}{}",
+                mark,
+                c.attribute.as_str(),
+                current,
+                desired,
+                note
+            );
+        }
+    }
+
+    /// Apply every mutable change in one `SetQueueAttributes` call; returns how
+    /// many attributes were set. Changes that need recreation are skipped.
+    pub async fn apply(&self, client: &Client, queue_url: &str) -> Result<usize> {
+        let attrs: HashMap<QueueAttributeName, String> = self
+            .changes
+            .iter()
+            .filter(|c| !c.requires_recreate())
+            .map(|c| (c.attribute.clone(), c.desired.clone()))
+            .collect();
+        if attrs.is_empty() {
+            return Ok(0);
+        }
+        let n = attrs.len();
+        client
+            .set_queue_attributes()
+            .queue_url(queue_url)
+            .set_attributes(Some(attrs))
+            .send()
+            .await
+            .with_context(|| format!("setting attributes of {}", self.queue_name))?;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use QueueAttributeName as A;
+
+    fn attrs(pairs: &[(A, &str)]) -> Vec<(A, String)> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.clone(), v.to_string()))
+            .collect()
+    }
+
+    fn live(pairs: &[(A, &str)]) -> HashMap<A, String> {
+        attrs(pairs).into_iter().collect()
+    }
+
+    fn change(attribute: A, current: Option<&str>, desired: &str) -> Change {
+        Change {
+            attribute,
+            current: current.map(str::to_string),
+            desired: desired.to_string(),
+        }
+    }
+
+    #[test]
+    fn no_op() {
+        let desired = attrs(&[
+            (A::FifoQueue, "true"),
+            (A::ContentBasedDeduplication, "true"),
+            (A::VisibilityTimeout, "30"),
+        ]);
+        // Booleans compare case-insensitively; unmanaged attributes are ignored
+        let actual = live(&[
+            (A::FifoQueue, "true"),
+            (A::ContentBasedDeduplication, "True"),
+            (A::VisibilityTimeout, "30"),
+            (A::DelaySeconds, "5"),
+        ]);
+        assert_eq!(diff(&desired, &actual), []);
+        assert_eq!(diff(&[], &live(&[(A::DelaySeconds, "5")])), []);
+    }
+
+    #[test]
+    fn updates() {
+        let desired = attrs(&[
+            (A::VisibilityTimeout, "3"),
+            (A::ReceiveMessageWaitTimeSeconds, "20"),
+            (A::DeduplicationScope, "messageGroup"),
+        ]);
+        let actual = live(&[
+            (A::VisibilityTimeout, "30"),
+            (A::ReceiveMessageWaitTimeSeconds, "20"),
+        ]);
+        assert_eq!(
+            diff(&desired, &actual),
+            [
+                change(A::VisibilityTimeout, Some("30"), "3"),
+                change(A::DeduplicationScope, None, "messageGroup"),
+            ]
+        );
+    }
+
+    #[test]
+    fn fifo_mismatch_requires_recreate() {
+        // SQS omits FifoQueue on standard queues
+        let to_fifo = attrs(&[(A::FifoQueue, "true")]);
+        let changes = diff(&to_fifo, &live(&[]));
+        assert_eq!(changes, [change(A::FifoQueue, Some("false"), "true")]);
+        assert!(changes[0].requires_recreate());
+
+        let to_standard = attrs(&[(A::VisibilityTimeout, "30")]);
+        let actual = live(&[(A::FifoQueue, "true"), (A::VisibilityTimeout, "3")]);
+        let plan = Plan {
+            queue_name: "orders".to_string(),
+            changes: diff(&to_standard, &actual),
+        };
+        assert_eq!(
+            plan.changes,
+            [
+                change(A::VisibilityTimeout, Some("3"), "30"),
+                change(A::FifoQueue, Some("true"), "false"),
+            ]
+        );
+        assert!(plan.needs_recreate());
+        assert!(!plan.changes[0].requires_recreate());
+    }
+
+    #[test]
+    fn sse_switch_turns_the_other_mode_off() {
+        let to_sqs = attrs(&[(A::SqsManagedSseEnabled, "true")]);
+        let kms = live(&[
+            (A::KmsMasterKeyId, "alias/aws/sqs"),
+            (A::SqsManagedSseEnabled, "false"),
+        ]);
+        assert_eq!(
+            diff(&to_sqs, &kms),
+            [
+                change(A::SqsManagedSseEnabled, Some("false"), "true"),
+                change(A::KmsMasterKeyId, Some("alias/aws/sqs"), ""),
+            ]
+        );
+
+        let to_kms = attrs(&[(A::KmsMasterKeyId, "alias/lab1")]);
+        let sse_sqs = live(&[(A::SqsManagedSseEnabled, "true")]);
+        assert_eq!(
+            diff(&to_kms, &sse_sqs),
+            [
+                change(A::KmsMasterKeyId, None, "alias/lab1"),
+                change(A::SqsManagedSseEnabled, Some("true"), "false"),
+            ]
+        );
+    }
+}
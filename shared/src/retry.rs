@@ -0,0 +1,69 @@
+//! Exponential backoff for failed messages using ChangeMessageVisibility.
+//! The delay grows with `ApproximateReceiveCount`, so no retry state is kept
+//! in the consumer: SQS counts the attempts for us.
+
+use anyhow::{Result, anyhow};
+use aws_sdk_sqs::Client;
+use aws_sdk_sqs::types::{Message, MessageSystemAttributeName};
+
+use crate::config::{AppConfig, RetryConfig};
+use crate::sqs;
+
+/// SQS caps a message's visibility timeout at 12 hours.
+const MAX_VISIBILITY_SECS: f64 = 43_200.0;
+
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub base_delay_secs: f64,
+    pub multiplier: f64,
+    pub max_delay_secs: f64,
+    pub jitter: f64,
+}
+
+impl RetryPolicy {
+    /// `None` when `[recv.retry]` is not configured.
+    pub fn from_config(cfg: &AppConfig) -> Option<Self> {
+        cfg.recv.retry.as_ref().map(Self::from_retry_config)
+    }
+
+    pub fn from_retry_config(rc: &RetryConfig) -> Self {
+        Self {
+            base_delay_secs: rc.base_delay_secs.unwrap_or(1.0).max(0.0),
+            multiplier: rc.multiplier.unwrap_or(2.0).max(1.0),
+            max_delay_secs: rc
+                .max_delay_secs
+                .unwrap_or(300.0)
+                .clamp(0.0, MAX_VISIBILITY_SECS),
+            jitter: rc.jitter.unwrap_or(0.2).clamp(0.0, 1.0),
+        }
+    }
+
+    /// Delay before the next attempt of a message received `receive_count` times (1-based).
+    /// The jittered part is drawn from `[delay * (1 - jitter), delay]`.
+    pub fn delay_secs(&self, receive_count: u32) -> i32 {
+        let exp = receive_count.saturating_sub(1).min(64) as i32;
+        let delay = (self.base_delay_secs * self.multiplier.powi(exp)).min(self.max_delay_secs);
+        let jittered = delay * (1.0 - self.jitter * fastrand::f64());
+        jittered.round() as i32
+    }
+
+    /// Hide a failed message for the backoff delay derived from its receive count.
+    /// The message must have been received with the `ApproximateReceiveCount` system attribute.
+    /// Returns the delay that was applied.
+    pub async fn backoff(&self, client: &Client, queue_url: &str, msg: &Message) -> Result<i32> {
+        let rh = msg
+            .receipt_handle()
+            .ok_or_else(|| anyhow!("missing receipt_handle; cannot back off"))?;
+        let delay = self.delay_secs(receive_count(msg));
+        sqs::change_visibility(client, queue_url, rh, delay).await?;
+        Ok(delay)
+    }
+}
+
+/// `ApproximateReceiveCount` of a received message (1 if it was not requested).
+pub fn receive_count(msg: &Message) -> u32 {
+    msg.attributes()
+        .and_then(|a| a.get(&MessageSystemAttributeName::ApproximateReceiveCount))
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(1)
+}
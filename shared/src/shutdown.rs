@@ -0,0 +1,41 @@
+//! Shutdown signals for long-running consumers (Ctrl+C everywhere, SIGTERM on Unix).
+
+use tracing::warn;
+
+/// Resolves on the next Ctrl+C (SIGINT) or SIGTERM and returns the signal name.
+/// Can be awaited again to catch a second signal.
+pub async fn signal() -> &'static str {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        let mut term = match signal(SignalKind::terminate()) {
+            Ok(term) => Some(term),
+            Err(e) => {
+                warn!("cannot listen for SIGTERM: {e}");
+                None
+            }
+        };
+        tokio::select! {
+            _ = ctrl_c() => "SIGINT",
+            Some(_) = async {
+                match term.as_mut() {
+                    Some(term) => term.recv().await,
+                    None => std::future::pending().await,
+                }
+            } => "SIGTERM",
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        ctrl_c().await;
+        "Ctrl+C"
+    }
+}
+
+async fn ctrl_c() {
+    if let Err(e) = tokio::signal::ctrl_c().await {
+        warn!("cannot listen for Ctrl+C: {e}");
+        std::future::pending::<()>().await;
+    }
+}
@@ -0,0 +1,354 @@
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{Context, Result, anyhow};
+use aws_sdk_sns as sns;
+use aws_sdk_sns::Client;
+use aws_sdk_sqs::types::MessageAttributeValue;
+use serde::Deserialize;
+use tracing::info;
+
+use crate::batch::BatchEntry;
+use crate::config::{AppConfig, SnsConfig, SubscriptionConfig, load_sdk_config};
+
+pub async fn build_sns_client(cfg: &AppConfig) -> Result<sns::Client> {
+    let shared_cfg = load_sdk_config(cfg, cfg.sns.endpoint_url.as_deref()).await;
+
+    let mut b = sns::config::Builder::from(&shared_cfg);
+    if let Some(ep) = &cfg.sns.endpoint_url {
+        b = b.endpoint_url(ep.clone());
+    }
+    Ok(sns::Client::from_conf(b.build()))
+}
+
+/// Look up a topic ARN by name (SNS has no GetTopicArn, so we page through ListTopics).
+pub async fn get_topic_arn(client: &Client, topic_name: &str) -> Result<String> {
+    let suffix = format!(":{topic_name}");
+    let mut next_token: Option<String> = None;
+    loop {
+        let out = client
+            .list_topics()
+            .set_next_token(next_token)
+            .send()
+            .await
+            .context("listing topics")?;
+
+        if let Some(arn) = out
+            .topics()
+            .iter()
+            .filter_map(|t| t.topic_arn())
+            .find(|arn| arn.ends_with(&suffix))
+        {
+            return Ok(arn.to_string());
+        }
+
+        match out.next_token() {
+            Some(t) => next_token = Some(t.to_string()),
+            None => return Err(anyhow!("topic {topic_name} not found")),
+        }
+    }
+}
+
+/// Attributes for `CreateTopic` derived from `[sns]`; rejects a FIFO setting
+/// that does not match the topic name. Makes no AWS call.
+pub fn topic_attributes(sns_cfg: &SnsConfig) -> Result<Vec<(&'static str, String)>> {
+    let name = sns_cfg
+        .topic_name
+        .as_deref()
+        .ok_or_else(|| anyhow!("SNS topic_name is required in [sns].topic_name"))?;
+
+    let mut attrs = Vec::new();
+
+    // FIFO handling: either explicitly set in config or inferred from name
+    let name_is_fifo = name.ends_with(".fifo");
+    let cfg_fifo = sns_cfg.fifo.unwrap_or(name_is_fifo);
+    if cfg_fifo {
+        if !name_is_fifo {
+            return Err(anyhow!(
+                "fifo=true requires the topic name to end with .fifo (got: {})",
+                name
+            ));
+        }
+        attrs.push(("FifoTopic", "true".to_string()));
+        if let Some(true) = sns_cfg.content_based_dedup {
+            attrs.push(("ContentBasedDeduplication", "true".to_string()));
+        }
+    } else if name_is_fifo {
+        return Err(anyhow!(
+            "Topic name ends with .fifo but fifo=false in [sns]. Either set fifo=true or rename the topic."
+        ));
+    }
+
+    Ok(attrs)
+}
+
+/// Create the topic from `[sns].topic_name` (idempotent: returns the existing ARN if present).
+pub async fn create_topic(client: &Client, sns_cfg: &SnsConfig) -> Result<String> {
+    let attrs = topic_attributes(sns_cfg)?;
+    let name = sns_cfg.topic_name.as_deref().unwrap_or_default();
+
+    let mut req = client.create_topic().name(name);
+    for (k, v) in attrs {
+        req = req.attributes(k, v);
+    }
+
+    let out = req
+        .send()
+        .await
+        .with_context(|| format!("creating topic {name}"))?;
+
+    out.topic_arn()
+        .map(|s| s.to_string())
+        .ok_or_else(|| anyhow!("topic arn missing after create"))
+}
+
+pub async fn delete_topic(client: &Client, topic_arn: &str) -> Result<()> {
+    client
+        .delete_topic()
+        .topic_arn(topic_arn)
+        .send()
+        .await
+        .context("deleting topic")?;
+    Ok(())
+}
+
+/// FIFO topics only deliver to FIFO queues, and standard topics cannot deliver to FIFO queues.
+/// Accepts names or ARNs.
+pub fn validate_subscription(topic: &str, queue: &str) -> Result<()> {
+    match (topic.ends_with(".fifo"), queue.ends_with(".fifo")) {
+        (true, false) => Err(anyhow!(
+            "Cannot subscribe standard queue {} to FIFO topic {}. Use a queue whose name ends with .fifo.",
+            queue,
+            topic
+        )),
+        (false, true) => Err(anyhow!(
+            "Cannot subscribe FIFO queue {} to standard topic {}. Use a topic whose name ends with .fifo.",
+            queue,
+            topic
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Desired subscription attributes for `sub`, in the order they should be applied
+/// (the scope goes before the policy so SNS validates the policy against it).
+pub fn subscription_attributes(sub: &SubscriptionConfig) -> Vec<(&'static str, String)> {
+    let raw = sub.raw_message_delivery.unwrap_or(false);
+    let mut attrs = vec![("RawMessageDelivery", raw.to_string())];
+    if let Some(fp) = &sub.filter_policy {
+        let scope = sub.filter_policy_scope.unwrap_or_default();
+        attrs.push(("FilterPolicyScope", scope.as_str().to_string()));
+        attrs.push(("FilterPolicy", fp.to_string()));
+    }
+    attrs
+}
+
+/// Find the confirmed SQS subscription of `queue_arn` on `topic_arn`, if any.
+pub async fn find_subscription(
+    client: &Client,
+    topic_arn: &str,
+    queue_arn: &str,
+) -> Result<Option<String>> {
+    let mut next_token: Option<String> = None;
+    loop {
+        let out = client
+            .list_subscriptions_by_topic()
+            .topic_arn(topic_arn)
+            .set_next_token(next_token)
+            .send()
+            .await
+            .with_context(|| format!("listing subscriptions of {topic_arn}"))?;
+
+        if let Some(arn) = out
+            .subscriptions()
+            .iter()
+            .filter(|s| s.protocol() == Some("sqs") && s.endpoint() == Some(queue_arn))
+            .filter_map(|s| s.subscription_arn())
+            .find(|arn| arn.starts_with("arn:"))
+        {
+            return Ok(Some(arn.to_string()));
+        }
+
+        match out.next_token() {
+            Some(t) => next_token = Some(t.to_string()),
+            None => return Ok(None),
+        }
+    }
+}
+
+/// Bring an existing subscription's attributes in line with `sub`.
+/// Returns the names of the attributes that were changed.
+pub async fn reconcile_subscription(
+    client: &Client,
+    subscription_arn: &str,
+    sub: &SubscriptionConfig,
+) -> Result<Vec<&'static str>> {
+    let out = client
+        .get_subscription_attributes()
+        .subscription_arn(subscription_arn)
+        .send()
+        .await
+        .with_context(|| format!("getting attributes of {subscription_arn}"))?;
+    let live = out.attributes().cloned().unwrap_or_default();
+
+    let mut desired = subscription_attributes(sub);
+    // A policy removed from the config must be removed from SNS as well
+    let live_policy = live.get("FilterPolicy").filter(|p| !p.is_empty() && *p != "{}");
+    if sub.filter_policy.is_none() && live_policy.is_some() {
+        desired.push(("FilterPolicy", "{}".to_string()));
+    }
+
+    let mut changed = Vec::new();
+    for (name, want) in desired {
+        let have = live.get(name).map(String::as_str);
+        let same = match name {
+            "FilterPolicy" => {
+                let parse = |s: &str| serde_json::from_str::<serde_json::Value>(s).ok();
+                have.and_then(parse) == parse(&want)
+            }
+            "RawMessageDelivery" => have.unwrap_or("false") == want,
+            "FilterPolicyScope" => have.unwrap_or("MessageAttributes") == want,
+            _ => have == Some(want.as_str()),
+        };
+        if same {
+            continue;
+        }
+
+        client
+            .set_subscription_attributes()
+            .subscription_arn(subscription_arn)
+            .attribute_name(name)
+            .attribute_value(want)
+            .send()
+            .await
+            .with_context(|| format!("setting {name} on {subscription_arn}"))?;
+        changed.push(name);
+    }
+    Ok(changed)
+}
+
+/// Subscribe an SQS queue (by ARN) to a topic and return the subscription ARN.
+/// If the subscription already exists its attributes are reconciled instead.
+pub async fn subscribe_queue(
+    client: &Client,
+    topic_arn: &str,
+    queue_arn: &str,
+    sub: &SubscriptionConfig,
+) -> Result<String> {
+    validate_subscription(topic_arn, queue_arn)?;
+
+    if let Some(arn) = find_subscription(client, topic_arn, queue_arn).await? {
+        let changed = reconcile_subscription(client, &arn, sub).await?;
+        if !changed.is_empty() {
+            info!("Updated subscription {}: {}", arn, changed.join(", "));
+        }
+        return Ok(arn);
+    }
+
+    let mut req = client
+        .subscribe()
+        .topic_arn(topic_arn)
+        .protocol("sqs")
+        .endpoint(queue_arn)
+        .return_subscription_arn(true);
+
+    for (name, value) in subscription_attributes(sub) {
+        req = req.attributes(name, value);
+    }
+
+    let out = req
+        .send()
+        .await
+        .with_context(|| format!("subscribing {queue_arn} to {topic_arn}"))?;
+
+    out.subscription_arn()
+        .map(|s| s.to_string())
+        .ok_or_else(|| anyhow!("subscription arn missing in response"))
+}
+
+/// Result of a successful [`publish`].
+#[derive(Debug, Clone)]
+pub struct Published {
+    pub message_id: String,
+    /// Only set for FIFO topics
+    pub sequence_number: Option<String>,
+}
+
+/// SNS takes the same attribute shape as SQS under its own SDK type.
+fn sns_attribute(v: &MessageAttributeValue) -> Result<sns::types::MessageAttributeValue> {
+    Ok(sns::types::MessageAttributeValue::builder()
+        .data_type(v.data_type())
+        .set_string_value(v.string_value().map(str::to_string))
+        .set_binary_value(
+            v.binary_value()
+                .map(|b| sns::primitives::Blob::new(b.as_ref())),
+        )
+        .build()?)
+}
+
+/// Publish `entry` (body, attributes and, for FIFO topics, group and dedup id);
+/// SNS copies the attributes to every subscriber.
+pub async fn publish(
+    client: &Client,
+    topic_arn: &str,
+    entry: &BatchEntry,
+    subject: Option<&str>,
+) -> Result<Published> {
+    let attributes = entry
+        .attributes
+        .iter()
+        .map(|(k, v)| Ok((k.clone(), sns_attribute(v)?)))
+        .collect::<Result<HashMap<_, _>>>()?;
+    let out = client
+        .publish()
+        .topic_arn(topic_arn)
+        .message(&entry.body)
+        .set_subject(subject.map(|s| s.to_string()))
+        .set_message_attributes(Some(attributes))
+        .set_message_group_id(entry.group_id.clone())
+        .set_message_deduplication_id(entry.dedup_id.clone())
+        .send()
+        .await
+        .with_context(|| format!("publishing to {topic_arn}"))?;
+
+    let message_id = out
+        .message_id()
+        .map(|s| s.to_string())
+        .ok_or_else(|| anyhow!("message id missing in publish response"))?;
+    Ok(Published {
+        message_id,
+        sequence_number: out.sequence_number().map(str::to_string),
+    })
+}
+
+/// JSON envelope SNS wraps around messages delivered to SQS without raw delivery.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct SnsEnvelope {
+    #[serde(rename = "Type")]
+    pub kind: String,
+    pub message_id: String,
+    pub topic_arn: String,
+    pub subject: Option<String>,
+    pub message: String,
+    pub timestamp: String,
+    #[serde(default)]
+    pub message_attributes: BTreeMap<String, SnsAttribute>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SnsAttribute {
+    #[serde(rename = "Type")]
+    pub data_type: String,
+    #[serde(rename = "Value")]
+    pub value: String,
+}
+
+/// Parse `body` as an SNS notification envelope; `None` for anything else.
+pub fn parse_envelope(body: &str) -> Option<SnsEnvelope> {
+    if !body.trim_start().starts_with('{') {
+        return None;
+    }
+    serde_json::from_str::<SnsEnvelope>(body)
+        .ok()
+        .filter(|env| env.kind == "Notification")
+}
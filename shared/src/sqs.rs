@@ -1,8 +1,18 @@
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+
 use anyhow::{Context, Result, anyhow};
 use aws_sdk_sqs::Client;
 use aws_sdk_sqs::types::QueueAttributeName;
+use serde_json::{Value, json};
+
+use crate::config::{DeduplicationScope, DlqConfig, FifoThroughputLimit, SqsConfig, SseMode};
+
+/// Used when `[sqs.dlq].max_receive_count` is not set.
+pub const DEFAULT_MAX_RECEIVE_COUNT: i32 = 5;
 
-use crate::config::SqsConfig;
+/// AWS managed key used for SSE-KMS when `kms_master_key_id` is not set.
+pub const DEFAULT_KMS_KEY: &str = "alias/aws/sqs";
 
 pub async fn get_queue_url(client: &Client, queue_name: &str) -> Result<String> {
     let out = client
@@ -17,13 +27,47 @@ pub async fn get_queue_url(client: &Client, queue_name: &str) -> Result<String>
         .ok_or_else(|| anyhow!("queue url missing in response"))
 }
 
-pub async fn create_queue(client: &Client, sqs_cfg: &SqsConfig) -> Result<String> {
-    let name = sqs_cfg
-        .queue_name
-        .as_deref()
-        .ok_or_else(|| anyhow!("SQS queue_name is required in [sqs].queue_name or --queue-name"))?;
-
+/// Create `name` with the attributes derived from `sqs_cfg`. The name is passed
+/// in (not read from `[sqs].queue_name`) so `--queue-name` and derived DLQ and
+/// subscriber names are honoured.
+pub async fn create_queue(client: &Client, name: &str, sqs_cfg: &SqsConfig) -> Result<String> {
+    // Everything is validated before the first AWS call
     let mut req = client.create_queue().queue_name(name);
+    for (k, v) in queue_attributes(name, sqs_cfg)? {
+        req = req.attributes(k, v);
+    }
+
+    let out = req
+        .send()
+        .await
+        .with_context(|| format!("creating queue {name}"))?;
+
+    out.queue_url()
+        .map(|s| s.to_string())
+        .ok_or_else(|| anyhow!("queue url missing after create"))
+}
+
+fn check_range(field: &str, value: i32, range: RangeInclusive<i32>) -> Result<()> {
+    if !range.contains(&value) {
+        return Err(anyhow!(
+            "[sqs].{} must be between {} and {} (got: {})",
+            field,
+            range.start(),
+            range.end(),
+            value
+        ));
+    }
+    Ok(())
+}
+
+/// Queue attributes `create_queue` sets for `name`, after checking every range
+/// and rejecting combinations SQS would refuse (FIFO-only settings on standard
+/// queues, high throughput without per-group deduplication).
+pub fn queue_attributes(
+    name: &str,
+    sqs_cfg: &SqsConfig,
+) -> Result<Vec<(QueueAttributeName, String)>> {
+    let mut attrs = Vec::new();
 
     // FIFO handling: either explicitly set in config or inferred from name
     let name_is_fifo = name.ends_with(".fifo");
@@ -35,29 +79,398 @@ pub async fn create_queue(client: &Client, sqs_cfg: &SqsConfig) -> Result<String
                 name
             ));
         }
-        req = req.attributes(QueueAttributeName::FifoQueue, "true");
-        if let Some(true) = sqs_cfg.content_based_dedup {
-            req = req.attributes(QueueAttributeName::ContentBasedDeduplication, "true");
+        attrs.push((QueueAttributeName::FifoQueue, "true".to_string()));
+        if let Some(dedup) = sqs_cfg.content_based_dedup {
+            attrs.push((
+                QueueAttributeName::ContentBasedDeduplication,
+                dedup.to_string(),
+            ));
+        }
+        if sqs_cfg.fifo_throughput_limit == Some(FifoThroughputLimit::PerMessageGroupId)
+            && sqs_cfg.deduplication_scope != Some(DeduplicationScope::MessageGroup)
+        {
+            return Err(anyhow!(
+                "[sqs].fifo_throughput_limit = \"perMessageGroupId\" (high throughput) requires deduplication_scope = \"messageGroup\""
+            ));
+        }
+        if let Some(scope) = sqs_cfg.deduplication_scope {
+            attrs.push((
+                QueueAttributeName::DeduplicationScope,
+                scope.as_str().into(),
+            ));
+        }
+        if let Some(limit) = sqs_cfg.fifo_throughput_limit {
+            attrs.push((
+                QueueAttributeName::FifoThroughputLimit,
+                limit.as_str().into(),
+            ));
         }
     } else if name_is_fifo {
         // User named it *.fifo but explicitly disabled FIFO
         return Err(anyhow!(
             "Queue name ends with .fifo but fifo=false in config. Either set fifo=true or rename the queue."
         ));
+    } else {
+        let fifo_only = [
+            (
+                "content_based_dedup",
+                sqs_cfg.content_based_dedup == Some(true),
+            ),
+            ("deduplication_scope", sqs_cfg.deduplication_scope.is_some()),
+            (
+                "fifo_throughput_limit",
+                sqs_cfg.fifo_throughput_limit.is_some(),
+            ),
+        ];
+        if let Some((field, _)) = fifo_only.iter().find(|(_, set)| *set) {
+            return Err(anyhow!(
+                "[sqs].{} is only valid for FIFO queues ({} is a standard queue)",
+                field,
+                name
+            ));
+        }
     }
 
-    if let Some(vt) = sqs_cfg.visibility_timeout_secs {
-        req = req.attributes(QueueAttributeName::VisibilityTimeout, vt.to_string());
+    let ranged = [
+        (
+            "visibility_timeout_secs",
+            QueueAttributeName::VisibilityTimeout,
+            sqs_cfg.visibility_timeout_secs,
+            0..=43_200,
+        ),
+        (
+            "delay_secs",
+            QueueAttributeName::DelaySeconds,
+            sqs_cfg.delay_secs,
+            0..=900,
+        ),
+        (
+            "message_retention_period_secs",
+            QueueAttributeName::MessageRetentionPeriod,
+            sqs_cfg.message_retention_period_secs,
+            60..=1_209_600,
+        ),
+        (
+            "maximum_message_size",
+            QueueAttributeName::MaximumMessageSize,
+            sqs_cfg.maximum_message_size,
+            1024..=262_144,
+        ),
+        (
+            "receive_message_wait_time_secs",
+            QueueAttributeName::ReceiveMessageWaitTimeSeconds,
+            sqs_cfg.receive_message_wait_time_secs,
+            0..=20,
+        ),
+    ];
+    for (field, attr, value, range) in ranged {
+        if let Some(v) = value {
+            check_range(field, v, range)?;
+            attrs.push((attr, v.to_string()));
+        }
     }
 
-    let out = req
+    attrs.extend(sse_attributes(sqs_cfg)?);
+    Ok(attrs)
+}
+
+/// Queue attributes for the `[sqs]` server-side encryption settings.
+pub fn sse_attributes(sqs_cfg: &SqsConfig) -> Result<Vec<(QueueAttributeName, String)>> {
+    let kms_fields_set =
+        sqs_cfg.kms_master_key_id.is_some() || sqs_cfg.kms_data_key_reuse_period_secs.is_some();
+    match sqs_cfg.sse {
+        None if kms_fields_set => Err(anyhow!(
+            "kms_master_key_id / kms_data_key_reuse_period_secs require sse = \"kms\""
+        )),
+        None => Ok(Vec::new()),
+        Some(SseMode::Sqs) if kms_fields_set => Err(anyhow!(
+            "sse = \"sqs\" uses SQS-managed keys; remove kms_master_key_id / kms_data_key_reuse_period_secs or set sse = \"kms\""
+        )),
+        Some(SseMode::Sqs) => Ok(vec![(
+            QueueAttributeName::SqsManagedSseEnabled,
+            "true".to_string(),
+        )]),
+        Some(SseMode::Kms) => {
+            let key = sqs_cfg
+                .kms_master_key_id
+                .as_deref()
+                .unwrap_or(DEFAULT_KMS_KEY);
+            let mut attrs = vec![(QueueAttributeName::KmsMasterKeyId, key.to_string())];
+            if let Some(reuse) = sqs_cfg.kms_data_key_reuse_period_secs {
+                check_range("kms_data_key_reuse_period_secs", reuse, 60..=86_400)?;
+                attrs.push((
+                    QueueAttributeName::KmsDataKeyReusePeriodSeconds,
+                    reuse.to_string(),
+                ));
+            }
+            Ok(attrs)
+        }
+    }
+}
+
+/// Human-readable encryption state from a queue's attributes.
+pub fn describe_encryption(attrs: &HashMap<QueueAttributeName, String>) -> String {
+    if let Some(key) = attrs
+        .get(&QueueAttributeName::KmsMasterKeyId)
+        .filter(|k| !k.is_empty())
+    {
+        let reuse = attrs
+            .get(&QueueAttributeName::KmsDataKeyReusePeriodSeconds)
+            .map_or(String::new(), |r| format!(", data key reused for {}s", r));
+        return format!("SSE-KMS (key {}{})", key, reuse);
+    }
+    match attrs
+        .get(&QueueAttributeName::SqsManagedSseEnabled)
+        .map(String::as_str)
+    {
+        Some("true") => "SSE-SQS (SQS-managed keys)".to_string(),
+        _ => "none (messages are not encrypted at rest)".to_string(),
+    }
+}
+
+/// Resolve the DLQ name for `queue_name`.
+/// FIFO main queues always get a FIFO DLQ, so ".fifo" is appended when missing.
+pub fn dlq_name(queue_name: &str, dlq: &DlqConfig) -> String {
+    let is_fifo = queue_name.ends_with(".fifo");
+    let base = dlq.name.clone().unwrap_or_else(|| {
+        let stem = queue_name.strip_suffix(".fifo").unwrap_or(queue_name);
+        format!("{stem}-dlq")
+    });
+    if is_fifo && !base.ends_with(".fifo") {
+        format!("{base}.fifo")
+    } else {
+        base
+    }
+}
+
+/// SQS only redrives between queues of the same type; reject a configured DLQ
+/// name whose FIFO-ness differs from the main queue before either is created.
+pub fn check_dlq_type(queue_name: &str, dlq_name: &str) -> Result<()> {
+    match (queue_name.ends_with(".fifo"), dlq_name.ends_with(".fifo")) {
+        (false, true) => Err(anyhow!(
+            "[sqs.dlq].name {} is a FIFO queue but {} is a standard queue; the DLQ must be standard too",
+            dlq_name,
+            queue_name
+        )),
+        (true, false) => Err(anyhow!(
+            "[sqs.dlq].name {} is a standard queue but {} is a FIFO queue; the DLQ must end with .fifo",
+            dlq_name,
+            queue_name
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Build the SqsConfig used to create the DLQ of `queue_name`, if one is configured.
+/// The DLQ mirrors the main queue's FIFO/dedup/visibility/encryption settings and
+/// its retention and size limits; redriven messages are not delayed again.
+pub fn dlq_config(sqs_cfg: &SqsConfig, queue_name: &str) -> Option<SqsConfig> {
+    let dlq = sqs_cfg.dlq.as_ref()?;
+    let name = dlq_name(queue_name, dlq);
+    let fifo = name.ends_with(".fifo");
+    Some(SqsConfig {
+        queue_name: Some(name),
+        endpoint_url: sqs_cfg.endpoint_url.clone(),
+        visibility_timeout_secs: sqs_cfg.visibility_timeout_secs,
+        delay_secs: None,
+        message_retention_period_secs: sqs_cfg.message_retention_period_secs,
+        maximum_message_size: sqs_cfg.maximum_message_size,
+        receive_message_wait_time_secs: sqs_cfg.receive_message_wait_time_secs,
+        fifo: Some(fifo),
+        content_based_dedup: if fifo {
+            sqs_cfg.content_based_dedup
+        } else {
+            None
+        },
+        deduplication_scope: sqs_cfg.deduplication_scope.filter(|_| fifo),
+        fifo_throughput_limit: sqs_cfg.fifo_throughput_limit.filter(|_| fifo),
+        sse: sqs_cfg.sse,
+        kms_master_key_id: sqs_cfg.kms_master_key_id.clone(),
+        kms_data_key_reuse_period_secs: sqs_cfg.kms_data_key_reuse_period_secs,
+        dlq: None,
+    })
+}
+
+/// Build the SqsConfig of an SNS subscriber queue: the main queue's settings
+/// without its DLQ, FIFO inferred from `queue_name`, and the FIFO-only settings
+/// dropped for standard subscribers.
+pub fn subscriber_config(sqs_cfg: &SqsConfig, queue_name: &str) -> SqsConfig {
+    let fifo = queue_name.ends_with(".fifo");
+    SqsConfig {
+        queue_name: Some(queue_name.to_string()),
+        fifo: None,
+        content_based_dedup: sqs_cfg.content_based_dedup.filter(|_| fifo),
+        deduplication_scope: sqs_cfg.deduplication_scope.filter(|_| fifo),
+        fifo_throughput_limit: sqs_cfg.fifo_throughput_limit.filter(|_| fifo),
+        dlq: None,
+        ..sqs_cfg.clone()
+    }
+}
+
+pub async fn get_queue_arn(client: &Client, queue_url: &str) -> Result<String> {
+    let out = client
+        .get_queue_attributes()
+        .queue_url(queue_url)
+        .attribute_names(QueueAttributeName::QueueArn)
         .send()
         .await
-        .with_context(|| format!("creating queue {name}"))?;
+        .with_context(|| format!("getting queue arn for {queue_url}"))?;
 
-    out.queue_url()
-        .map(|s| s.to_string())
-        .ok_or_else(|| anyhow!("queue url missing after create"))
+    out.attributes()
+        .and_then(|m| m.get(&QueueAttributeName::QueueArn))
+        .cloned()
+        .ok_or_else(|| anyhow!("QueueArn missing in response"))
+}
+
+/// Resolve `[sqs.dlq].max_receive_count` (default [`DEFAULT_MAX_RECEIVE_COUNT`]),
+/// rejecting values outside 1..=1000.
+pub fn check_max_receive_count(max_receive_count: Option<i32>) -> Result<i32> {
+    let max = max_receive_count.unwrap_or(DEFAULT_MAX_RECEIVE_COUNT);
+    if !(1..=1000).contains(&max) {
+        return Err(anyhow!(
+            "[sqs.dlq].max_receive_count must be between 1 and 1000 (got: {})",
+            max
+        ));
+    }
+    Ok(max)
+}
+
+/// Attach a RedrivePolicy so messages received more than `max_receive_count`
+/// times are moved to the DLQ identified by `dlq_arn`.
+pub async fn set_redrive_policy(
+    client: &Client,
+    queue_url: &str,
+    dlq_arn: &str,
+    max_receive_count: Option<i32>,
+) -> Result<()> {
+    let max = check_max_receive_count(max_receive_count)?;
+    let policy = json!({
+        "deadLetterTargetArn": dlq_arn,
+        "maxReceiveCount": max.to_string(),
+    });
+
+    client
+        .set_queue_attributes()
+        .queue_url(queue_url)
+        .attributes(QueueAttributeName::RedrivePolicy, policy.to_string())
+        .send()
+        .await
+        .context("setting RedrivePolicy")?;
+    Ok(())
+}
+
+/// Restrict which source queues may use `dlq_url` as their dead-letter queue.
+pub async fn set_redrive_allow_policy(
+    client: &Client,
+    dlq_url: &str,
+    source_arn: &str,
+) -> Result<()> {
+    let policy = json!({
+        "redrivePermission": "byQueue",
+        "sourceQueueArns": [source_arn],
+    });
+
+    client
+        .set_queue_attributes()
+        .queue_url(dlq_url)
+        .attributes(QueueAttributeName::RedriveAllowPolicy, policy.to_string())
+        .send()
+        .await
+        .context("setting RedriveAllowPolicy")?;
+    Ok(())
+}
+
+/// Fetch the queue's access policy (the `Policy` attribute), if any.
+pub async fn get_queue_policy(client: &Client, queue_url: &str) -> Result<Option<Value>> {
+    let out = client
+        .get_queue_attributes()
+        .queue_url(queue_url)
+        .attribute_names(QueueAttributeName::Policy)
+        .send()
+        .await
+        .context("getting queue policy")?;
+
+    match out
+        .attributes()
+        .and_then(|m| m.get(&QueueAttributeName::Policy))
+    {
+        Some(raw) if !raw.is_empty() => {
+            let v = serde_json::from_str(raw).context("parsing queue policy JSON")?;
+            Ok(Some(v))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Merge a statement allowing `topic_arn` to `sqs:SendMessage` into `policy`.
+/// Statements for other sources are preserved; an existing statement for the
+/// same `aws:SourceArn` is replaced so repeated bootstraps stay idempotent.
+pub fn merge_sns_send_statement(policy: Option<Value>, queue_arn: &str, topic_arn: &str) -> Value {
+    let mut policy = policy.unwrap_or_else(|| json!({ "Version": "2012-10-17" }));
+
+    let mut statements = match policy.get_mut("Statement").map(Value::take) {
+        Some(Value::Array(v)) => v,
+        Some(Value::Null) | None => Vec::new(),
+        // A policy may hold a single statement object instead of an array
+        Some(single) => vec![single],
+    };
+
+    statements.retain(|st| {
+        st.pointer("/Condition/ArnEquals/aws:SourceArn")
+            .and_then(Value::as_str)
+            != Some(topic_arn)
+    });
+
+    let topic_name = topic_arn.rsplit(':').next().unwrap_or(topic_arn);
+    statements.push(json!({
+        "Sid": format!("AllowSNS-{}", topic_name.replace('.', "-")),
+        "Effect": "Allow",
+        "Principal": { "Service": "sns.amazonaws.com" },
+        "Action": "sqs:SendMessage",
+        "Resource": queue_arn,
+        "Condition": { "ArnEquals": { "aws:SourceArn": topic_arn } },
+    }));
+
+    policy["Statement"] = Value::Array(statements);
+    policy
+}
+
+/// Ensure the queue policy lets `topic_arn` deliver to this queue.
+pub async fn allow_sns_send(
+    client: &Client,
+    queue_url: &str,
+    queue_arn: &str,
+    topic_arn: &str,
+) -> Result<()> {
+    let current = get_queue_policy(client, queue_url).await?;
+    let merged = merge_sns_send_statement(current, queue_arn, topic_arn);
+
+    client
+        .set_queue_attributes()
+        .queue_url(queue_url)
+        .attributes(QueueAttributeName::Policy, merged.to_string())
+        .send()
+        .await
+        .context("setting queue Policy")?;
+    Ok(())
+}
+
+/// Set a message's visibility timeout relative to now (0 makes it visible immediately).
+pub async fn change_visibility(
+    client: &Client,
+    queue_url: &str,
+    receipt_handle: &str,
+    visibility_timeout_secs: i32,
+) -> Result<()> {
+    client
+        .change_message_visibility()
+        .queue_url(queue_url)
+        .receipt_handle(receipt_handle)
+        .visibility_timeout(visibility_timeout_secs)
+        .send()
+        .await
+        .context("changing message visibility")?;
+    Ok(())
 }
 
 pub async fn purge_queue(client: &Client, queue_url: &str) -> Result<()> {
@@ -94,6 +507,224 @@ pub async fn print_attrs(client: &Client, queue_url: &str) -> Result<()> {
         for (k, v) in map {
             println!("[attr] {k} = {v}");
         }
+        println!("[attr] encryption: {}", describe_encryption(map));
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn err(name: &str, cfg: &SqsConfig) -> String {
+        queue_attributes(name, cfg).unwrap_err().to_string()
+    }
+
+    fn high_throughput() -> SqsConfig {
+        SqsConfig {
+            content_based_dedup: Some(true),
+            deduplication_scope: Some(DeduplicationScope::MessageGroup),
+            fifo_throughput_limit: Some(FifoThroughputLimit::PerMessageGroupId),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn ranges() {
+        let cases = [
+            (
+                SqsConfig {
+                    visibility_timeout_secs: Some(43_201),
+                    ..Default::default()
+                },
+                "[sqs].visibility_timeout_secs must be between 0 and 43200 (got: 43201)",
+            ),
+            (
+                SqsConfig {
+                    delay_secs: Some(-1),
+                    ..Default::default()
+                },
+                "[sqs].delay_secs must be between 0 and 900 (got: -1)",
+            ),
+            (
+                SqsConfig {
+                    message_retention_period_secs: Some(59),
+                    ..Default::default()
+                },
+                "[sqs].message_retention_period_secs must be between 60 and 1209600 (got: 59)",
+            ),
+            (
+                SqsConfig {
+                    maximum_message_size: Some(262_145),
+                    ..Default::default()
+                },
+                "[sqs].maximum_message_size must be between 1024 and 262144 (got: 262145)",
+            ),
+            (
+                SqsConfig {
+                    receive_message_wait_time_secs: Some(21),
+                    ..Default::default()
+                },
+                "[sqs].receive_message_wait_time_secs must be between 0 and 20 (got: 21)",
+            ),
+            (
+                SqsConfig {
+                    sse: Some(SseMode::Kms),
+                    kms_data_key_reuse_period_secs: Some(59),
+                    ..Default::default()
+                },
+                "[sqs].kms_data_key_reuse_period_secs must be between 60 and 86400 (got: 59)",
+            ),
+        ];
+        for (cfg, want) in cases {
+            assert_eq!(err("orders", &cfg), want);
+        }
+
+        let bounds = SqsConfig {
+            visibility_timeout_secs: Some(0),
+            delay_secs: Some(900),
+            message_retention_period_secs: Some(60),
+            maximum_message_size: Some(1024),
+            receive_message_wait_time_secs: Some(20),
+            ..Default::default()
+        };
+        assert_eq!(queue_attributes("orders", &bounds).unwrap().len(), 5);
+    }
+
+    #[test]
+    fn fifo_only_settings_on_standard_queue() {
+        let cases = [
+            (
+                SqsConfig {
+                    content_based_dedup: Some(true),
+                    ..Default::default()
+                },
+                "content_based_dedup",
+            ),
+            (
+                SqsConfig {
+                    deduplication_scope: Some(DeduplicationScope::Queue),
+                    ..Default::default()
+                },
+                "deduplication_scope",
+            ),
+            (
+                SqsConfig {
+                    fifo_throughput_limit: Some(FifoThroughputLimit::PerQueue),
+                    ..Default::default()
+                },
+                "fifo_throughput_limit",
+            ),
+        ];
+        for (cfg, field) in cases {
+            assert_eq!(
+                err("orders", &cfg),
+                format!("[sqs].{field} is only valid for FIFO queues (orders is a standard queue)")
+            );
+        }
+        // content_based_dedup = false is what standard queues have anyway
+        let off = SqsConfig {
+            content_based_dedup: Some(false),
+            ..Default::default()
+        };
+        assert!(queue_attributes("orders", &off).unwrap().is_empty());
+    }
+
+    #[test]
+    fn fifo_name_and_flag_must_agree() {
+        let fifo = SqsConfig {
+            fifo: Some(true),
+            ..Default::default()
+        };
+        assert!(err("orders", &fifo).starts_with("fifo=true requires"));
+        let standard = SqsConfig {
+            fifo: Some(false),
+            ..Default::default()
+        };
+        assert!(err("orders.fifo", &standard).starts_with("Queue name ends with .fifo"));
+    }
+
+    #[test]
+    fn high_throughput_fifo() {
+        let attrs = queue_attributes("orders.fifo", &high_throughput()).unwrap();
+        assert_eq!(
+            attrs,
+            [
+                (QueueAttributeName::FifoQueue, "true".to_string()),
+                (
+                    QueueAttributeName::ContentBasedDeduplication,
+                    "true".to_string()
+                ),
+                (
+                    QueueAttributeName::DeduplicationScope,
+                    "messageGroup".to_string()
+                ),
+                (
+                    QueueAttributeName::FifoThroughputLimit,
+                    "perMessageGroupId".to_string()
+                ),
+            ]
+        );
+
+        for scope in [None, Some(DeduplicationScope::Queue)] {
+            let cfg = SqsConfig {
+                deduplication_scope: scope,
+                ..high_throughput()
+            };
+            assert!(
+                err("orders.fifo", &cfg)
+                    .contains("requires deduplication_scope = \"messageGroup\"")
+            );
+        }
+    }
+
+    #[test]
+    fn subscriber_config_drops_fifo_only_settings() {
+        let main = SqsConfig {
+            queue_name: Some("orders.fifo".to_string()),
+            visibility_timeout_secs: Some(45),
+            ..high_throughput()
+        };
+
+        let standard = subscriber_config(&main, "audit");
+        assert_eq!(standard.queue_name.as_deref(), Some("audit"));
+        assert_eq!(standard.visibility_timeout_secs, Some(45));
+        assert_eq!(standard.content_based_dedup, None);
+        assert_eq!(standard.deduplication_scope, None);
+        assert_eq!(standard.fifo_throughput_limit, None);
+        assert!(queue_attributes("audit", &standard).is_ok());
+
+        let fifo = subscriber_config(&main, "billing.fifo");
+        assert_eq!(
+            fifo.fifo_throughput_limit,
+            Some(FifoThroughputLimit::PerMessageGroupId)
+        );
+        assert!(queue_attributes("billing.fifo", &fifo).is_ok());
+    }
+
+    #[test]
+    fn dlq_type_must_match() {
+        let dlq = |name: &str| DlqConfig {
+            name: Some(name.to_string()),
+            ..Default::default()
+        };
+        let standard_to_fifo = dlq_name("orders", &dlq("orders-dlq.fifo"));
+        assert!(check_dlq_type("orders", &standard_to_fifo).is_err());
+        // A FIFO main queue gets ".fifo" appended to its DLQ name
+        let fifo = dlq_name("orders.fifo", &dlq("orders-dlq"));
+        assert_eq!(fifo, "orders-dlq.fifo");
+        assert!(check_dlq_type("orders.fifo", &fifo).is_ok());
+        assert!(check_dlq_type("orders", &dlq_name("orders", &dlq("orders-dlq"))).is_ok());
+    }
+
+    #[test]
+    fn max_receive_count() {
+        assert_eq!(
+            check_max_receive_count(None).unwrap(),
+            DEFAULT_MAX_RECEIVE_COUNT
+        );
+        assert_eq!(check_max_receive_count(Some(1000)).unwrap(), 1000);
+        assert!(check_max_receive_count(Some(0)).is_err());
+        assert!(check_max_receive_count(Some(1001)).is_err());
+    }
+}
@@ -0,0 +1,541 @@
+//! Typed message bodies: [`Producer<T>`] encodes events as JSON and stamps
+//! `content-type` / `message-type` attributes; [`Consumer<T, H>`] decodes them
+//! before calling a [`TypedHandler`] and routes undecodable messages to a
+//! [`PoisonHandler`] instead of failing the loop.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::marker::PhantomData;
+
+use anyhow::{Context, Result};
+use aws_sdk_sqs::Client;
+use aws_sdk_sqs::types::{Message, MessageAttributeValue};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use tracing::warn;
+
+use crate::attrs::MAX_ATTRIBUTES;
+use crate::batch::{self, BatchEntry, EntryOutcome};
+use crate::consumer::{self, Ack, ConsumerOptions, Handler};
+use crate::encryption::{DecryptionError, Envelope};
+use crate::fifo;
+use crate::offload::Offloader;
+use crate::sns;
+
+pub const CONTENT_TYPE_ATTR: &str = "content-type";
+pub const MESSAGE_TYPE_ATTR: &str = "message-type";
+pub const JSON_CONTENT_TYPE: &str = "application/json";
+/// Added to forwarded poison messages (see [`PoisonAction::Forward`]).
+pub const POISON_REASON_ATTR: &str = "poison-reason";
+
+/// Default `message-type` for `T`: its type name without the module path.
+pub fn default_message_type<T>() -> &'static str {
+    let name = std::any::type_name::<T>();
+    let base = name.split('<').next().unwrap_or(name);
+    base.rsplit("::").next().unwrap_or(base)
+}
+
+fn string_attr(value: &str) -> Result<MessageAttributeValue> {
+    Ok(MessageAttributeValue::builder()
+        .data_type("String")
+        .string_value(value)
+        .build()?)
+}
+
+/// Sends `T` values as JSON bodies to one queue.
+pub struct Producer<T> {
+    client: Client,
+    queue_url: String,
+    message_type: String,
+    offload: Option<Offloader>,
+    encryption: Option<Envelope>,
+    _event: PhantomData<fn(&T)>,
+}
+
+impl<T: Serialize> Producer<T> {
+    pub fn new(client: Client, queue_url: impl Into<String>) -> Self {
+        Self {
+            client,
+            queue_url: queue_url.into(),
+            message_type: default_message_type::<T>().to_string(),
+            offload: None,
+            encryption: None,
+            _event: PhantomData,
+        }
+    }
+
+    /// Upload oversized bodies to S3 and send pointer messages instead.
+    pub fn with_offload(mut self, offload: Option<Offloader>) -> Self {
+        self.offload = offload;
+        self
+    }
+
+    /// Encrypt bodies client-side before they are sent (and offloaded).
+    pub fn with_encryption(mut self, encryption: Option<Envelope>) -> Self {
+        self.encryption = encryption;
+        self
+    }
+
+    /// Override the `message-type` attribute (defaults to the Rust type name).
+    pub fn with_message_type(mut self, message_type: impl Into<String>) -> Self {
+        self.message_type = message_type.into();
+        self
+    }
+
+    pub fn message_type(&self) -> &str {
+        &self.message_type
+    }
+
+    /// Encode `event` into a batch entry carrying the content/message type attributes.
+    pub fn entry(&self, event: &T) -> Result<BatchEntry> {
+        let body = serde_json::to_string(event)
+            .with_context(|| format!("serialize {}", self.message_type))?;
+        let attributes = HashMap::from([
+            (
+                CONTENT_TYPE_ATTR.to_string(),
+                string_attr(JSON_CONTENT_TYPE)?,
+            ),
+            (
+                MESSAGE_TYPE_ATTR.to_string(),
+                string_attr(&self.message_type)?,
+            ),
+        ]);
+        Ok(BatchEntry {
+            body,
+            attributes,
+            ..Default::default()
+        })
+    }
+
+    /// Send one event; returns the message id.
+    pub async fn send(&self, event: &T) -> Result<String> {
+        self.send_entry(self.entry(event)?).await
+    }
+
+    /// Send one event to a FIFO queue.
+    pub async fn send_to_group(
+        &self,
+        event: &T,
+        group: &str,
+        dedup: Option<&str>,
+    ) -> Result<String> {
+        let entry = BatchEntry {
+            group_id: Some(group.to_string()),
+            dedup_id: dedup.map(str::to_string),
+            ..self.entry(event)?
+        };
+        self.send_entry(entry).await
+    }
+
+    /// Send many events with SendMessageBatch (see [`batch::send_all`]).
+    pub async fn send_all(&self, events: &[T]) -> Result<Vec<EntryOutcome>> {
+        let mut entries = events
+            .iter()
+            .map(|e| self.entry(e))
+            .collect::<Result<Vec<_>>>()?;
+        for e in entries.iter_mut() {
+            *e = self.prepare(std::mem::take(e)).await?;
+        }
+        batch::send_all(&self.client, &self.queue_url, &entries).await
+    }
+
+    /// Encrypt, then offload, as configured.
+    async fn prepare(&self, entry: BatchEntry) -> Result<BatchEntry> {
+        let entry = match &self.encryption {
+            Some(env) => env.seal(entry).await?,
+            None => entry,
+        };
+        match &self.offload {
+            Some(off) => off.offload(entry).await,
+            None => Ok(entry),
+        }
+    }
+
+    async fn send_entry(&self, entry: BatchEntry) -> Result<String> {
+        let entry = self.prepare(entry).await?;
+        let out = self
+            .client
+            .send_message()
+            .queue_url(&self.queue_url)
+            .message_body(entry.body)
+            .set_message_attributes(Some(entry.attributes))
+            .set_message_group_id(entry.group_id)
+            .set_message_deduplication_id(entry.dedup_id)
+            .send()
+            .await
+            .with_context(|| format!("send_message ({})", self.message_type))?;
+        Ok(out.message_id().unwrap_or("unknown").to_string())
+    }
+}
+
+/// Why a message could not be turned into a `T`.
+#[derive(Debug)]
+pub enum DecodeError {
+    /// Encrypted body that cannot be opened (see [`DecryptionError`])
+    Decryption(DecryptionError),
+    /// `content-type` attribute present but not JSON
+    ContentType(String),
+    /// `message-type` attribute present but names another type
+    MessageType { expected: String, found: String },
+    /// Body is not valid JSON for `T`
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Decryption(e) => write!(f, "{}", e),
+            Self::ContentType(ct) => write!(f, "unsupported content-type {:?}", ct),
+            Self::MessageType { expected, found } => {
+                write!(f, "message-type {:?}, expected {:?}", found, expected)
+            }
+            Self::Json(e) => write!(f, "invalid JSON body: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Decode a message body into `T`.
+/// SNS envelopes are unwrapped first and their attributes are checked instead.
+/// Missing `content-type`/`message-type` attributes are accepted (plain JSON).
+pub fn decode<T: DeserializeOwned>(m: &Message, message_type: &str) -> Result<T, DecodeError> {
+    let body = m.body().unwrap_or("");
+    let envelope = sns::parse_envelope(body);
+
+    let attr = |name: &str| -> Option<String> {
+        match &envelope {
+            Some(env) => env.message_attributes.get(name).map(|a| a.value.clone()),
+            None => m
+                .message_attributes()
+                .and_then(|a| a.get(name))
+                .and_then(|v| v.string_value())
+                .map(str::to_string),
+        }
+    };
+
+    if let Some(ct) = attr(CONTENT_TYPE_ATTR)
+        && !ct.starts_with(JSON_CONTENT_TYPE)
+    {
+        return Err(DecodeError::ContentType(ct));
+    }
+    if let Some(found) = attr(MESSAGE_TYPE_ATTR)
+        && found != message_type
+    {
+        return Err(DecodeError::MessageType {
+            expected: message_type.to_string(),
+            found,
+        });
+    }
+
+    let body = envelope.as_ref().map_or(body, |env| env.message.as_str());
+    serde_json::from_str(body).map_err(DecodeError::Json)
+}
+
+/// Per-event processing logic for a [`Consumer<T, H>`].
+pub trait TypedHandler<T>: Send + Sync {
+    fn handle(&self, event: T, msg: &Message) -> impl Future<Output = Result<Ack>> + Send;
+}
+
+/// Decides what happens to a message whose body cannot be decrypted or decoded.
+/// `msg` is the message as received: before its offloaded body is fetched,
+/// decrypted or decompressed.
+pub trait PoisonHandler: Send + Sync {
+    fn poison(&self, msg: &Message, err: &DecodeError) -> impl Future<Output = Result<Ack>> + Send;
+}
+
+/// Built-in poison handling.
+#[derive(Debug, Clone, Default)]
+pub enum PoisonAction {
+    /// Leave it: redelivered until the DLQ's maxReceiveCount moves it away
+    #[default]
+    Leave,
+    /// Drop it
+    Delete,
+    /// Copy the received body and attributes to another queue, then delete it
+    /// (an offloaded payload is kept for the copy); a `poison-reason` attribute is
+    /// added unless the 10-attribute limit is reached. A FIFO target gets the
+    /// original group (or `poison`) and the message id as deduplication id.
+    Forward { client: Client, queue_url: String },
+}
+
+impl PoisonHandler for PoisonAction {
+    async fn poison(&self, m: &Message, err: &DecodeError) -> Result<Ack> {
+        let mid = m.message_id().unwrap_or("unknown");
+        warn!("poison message_id={}: {}", mid, err);
+        match self {
+            Self::Leave => Ok(Ack::Leave),
+            Self::Delete => Ok(Ack::Delete),
+            Self::Forward { client, queue_url } => {
+                let mut attrs = m.message_attributes().cloned().unwrap_or_default();
+                // The original attributes win if there is no room for the reason
+                if attrs.len() < MAX_ATTRIBUTES || attrs.contains_key(POISON_REASON_ATTR) {
+                    attrs.insert(
+                        POISON_REASON_ATTR.to_string(),
+                        string_attr(&err.to_string())?,
+                    );
+                } else {
+                    warn!(
+                        "message_id={} already has {} attributes; forwarding without {}",
+                        mid, MAX_ATTRIBUTES, POISON_REASON_ATTR
+                    );
+                }
+                let mut req = client
+                    .send_message()
+                    .queue_url(queue_url)
+                    .message_body(m.body().unwrap_or(""))
+                    .set_message_attributes(Some(attrs));
+                if queue_url.ends_with(".fifo") {
+                    let group = fifo::group_id(m).unwrap_or("poison");
+                    req = req.message_group_id(group).message_deduplication_id(mid);
+                }
+                req.send().await.context("forward poison message")?;
+                Ok(Ack::Moved)
+            }
+        }
+    }
+}
+
+/// Adapts a [`TypedHandler`] to the untyped [`Handler`] the runner expects.
+struct Decoding<T, H, P> {
+    handler: H,
+    poison: P,
+    message_type: String,
+    _event: PhantomData<fn() -> T>,
+}
+
+impl<T, H, P> Handler for Decoding<T, H, P>
+where
+    T: DeserializeOwned + Send,
+    H: TypedHandler<T>,
+    P: PoisonHandler,
+{
+    async fn handle(&self, m: &Message) -> Result<Ack> {
+        self.handle_received(m, m).await
+    }
+
+    async fn handle_received(&self, received: &Message, m: &Message) -> Result<Ack> {
+        match decode::<T>(m, &self.message_type) {
+            Ok(event) => self.handler.handle(event, m).await,
+            Err(e) => self.poison.poison(received, &e).await,
+        }
+    }
+
+    async fn poison(&self, m: &Message, err: DecryptionError) -> Result<Ack> {
+        self.poison.poison(m, &DecodeError::Decryption(err)).await
+    }
+}
+
+/// Runs a [`TypedHandler`] on top of [`consumer::Consumer`].
+pub struct Consumer<T, H, P = PoisonAction> {
+    inner: consumer::Consumer<Decoding<T, H, P>>,
+}
+
+impl<T, H> Consumer<T, H>
+where
+    T: DeserializeOwned + Send + 'static,
+    H: TypedHandler<T> + 'static,
+{
+    /// Expects `message-type` = the Rust type name of `T`; poison messages are left.
+    pub fn new(
+        client: Client,
+        queue_url: impl Into<String>,
+        handler: H,
+        opts: ConsumerOptions,
+    ) -> Self {
+        Self::with_poison(
+            client,
+            queue_url,
+            handler,
+            PoisonAction::default(),
+            default_message_type::<T>(),
+            opts,
+        )
+    }
+}
+
+impl<T, H, P> Consumer<T, H, P>
+where
+    T: DeserializeOwned + Send + 'static,
+    H: TypedHandler<T> + 'static,
+    P: PoisonHandler + 'static,
+{
+    pub fn with_poison(
+        client: Client,
+        queue_url: impl Into<String>,
+        handler: H,
+        poison: P,
+        message_type: &str,
+        opts: ConsumerOptions,
+    ) -> Self {
+        let decoding = Decoding {
+            handler,
+            poison,
+            message_type: message_type.to_string(),
+            _event: PhantomData,
+        };
+        Self {
+            inner: consumer::Consumer::new(client, queue_url, decoding, opts),
+        }
+    }
+
+    /// See [`consumer::Consumer::with_offload`].
+    pub fn with_offload(mut self, offload: Option<Offloader>) -> Self {
+        self.inner = self.inner.with_offload(offload);
+        self
+    }
+
+    /// See [`consumer::Consumer::with_encryption`].
+    pub fn with_encryption(mut self, encryption: Option<Envelope>) -> Self {
+        self.inner = self.inner.with_encryption(encryption);
+        self
+    }
+
+    pub fn stats(&self) -> &consumer::ConsumerStats {
+        self.inner.stats()
+    }
+
+    pub fn print_settings(&self) {
+        self.inner.print_settings();
+    }
+
+    pub async fn run(self) -> Result<()> {
+        self.inner.run().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use aws_sdk_sqs::operation::send_message::SendMessageOutput;
+    use aws_sdk_sqs::types::MessageSystemAttributeName;
+    use aws_smithy_mocks::{mock, mock_client};
+
+    use super::*;
+
+    fn message_with_attrs(n: usize) -> Message {
+        let mut b = Message::builder().message_id("m1").body("not json");
+        for i in 0..n {
+            b = b.message_attributes(format!("a{i}"), string_attr("v").unwrap());
+        }
+        b.build()
+    }
+
+    /// Forward `m` as poison and return the attribute names that were sent.
+    async fn forward(m: &Message) -> Vec<String> {
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let record = sent.clone();
+        let send = mock!(Client::send_message)
+            .match_requests(move |req| {
+                let mut names: Vec<String> = req
+                    .message_attributes()
+                    .map(|a| a.keys().cloned().collect())
+                    .unwrap_or_default();
+                names.sort();
+                *record.lock().unwrap() = names;
+                true
+            })
+            .then_output(|| SendMessageOutput::builder().message_id("f1").build());
+        let action = PoisonAction::Forward {
+            client: mock_client!(aws_sdk_sqs, [&send]),
+            queue_url: "https://sqs.local/poison".to_string(),
+        };
+
+        let err = DecodeError::ContentType("text/plain".to_string());
+        assert_eq!(action.poison(m, &err).await.unwrap(), Ack::Moved);
+        assert_eq!(send.num_calls(), 1);
+        sent.lock().unwrap().clone()
+    }
+
+    #[tokio::test]
+    async fn forward_adds_poison_reason() {
+        let names = forward(&message_with_attrs(2)).await;
+        assert_eq!(names, ["a0", "a1", POISON_REASON_ATTR]);
+    }
+
+    #[tokio::test]
+    async fn forward_keeps_attribute_limit() {
+        let names = forward(&message_with_attrs(MAX_ATTRIBUTES)).await;
+        assert_eq!(names.len(), MAX_ATTRIBUTES);
+        assert!(!names.iter().any(|n| n == POISON_REASON_ATTR));
+    }
+
+    #[tokio::test]
+    async fn forward_to_fifo_keeps_group() {
+        let send = mock!(Client::send_message)
+            .match_requests(|req| {
+                req.message_group_id() == Some("g1") && req.message_deduplication_id() == Some("m1")
+            })
+            .then_output(|| SendMessageOutput::builder().message_id("f1").build());
+        let action = PoisonAction::Forward {
+            client: mock_client!(aws_sdk_sqs, [&send]),
+            queue_url: "https://sqs.local/poison.fifo".to_string(),
+        };
+
+        let m = Message::builder()
+            .message_id("m1")
+            .body("not json")
+            .attributes(MessageSystemAttributeName::MessageGroupId, "g1")
+            .build();
+        let err = DecodeError::ContentType("text/plain".to_string());
+        assert_eq!(action.poison(&m, &err).await.unwrap(), Ack::Moved);
+        assert_eq!(send.num_calls(), 1);
+    }
+
+    /// Records what it was called with.
+    #[derive(Default)]
+    struct Recording(Mutex<Vec<String>>);
+
+    impl PoisonHandler for Recording {
+        async fn poison(&self, m: &Message, err: &DecodeError) -> Result<Ack> {
+            let body = m.body().unwrap_or("");
+            self.0.lock().unwrap().push(format!("{body}: {err:?}"));
+            Ok(Ack::Delete)
+        }
+    }
+
+    struct Unused;
+
+    impl TypedHandler<u32> for Unused {
+        async fn handle(&self, _event: u32, _msg: &Message) -> Result<Ack> {
+            unreachable!("poison messages never reach the typed handler")
+        }
+    }
+
+    fn decoding() -> Decoding<u32, Unused, Recording> {
+        Decoding {
+            handler: Unused,
+            poison: Recording::default(),
+            message_type: "u32".to_string(),
+            _event: PhantomData,
+        }
+    }
+
+    #[tokio::test]
+    async fn decryption_failures_reach_the_poison_handler() {
+        let decoding = decoding();
+        let m = Message::builder().message_id("m1").build();
+        let ack = Handler::poison(&decoding, &m, DecryptionError::Tampered)
+            .await
+            .unwrap();
+        assert_eq!(ack, Ack::Delete);
+        assert_eq!(
+            *decoding.poison.0.lock().unwrap(),
+            [": Decryption(Tampered)"]
+        );
+    }
+
+    #[tokio::test]
+    async fn poison_handler_gets_the_received_message() {
+        let decoding = decoding();
+        let received = Message::builder().message_id("m1").body("pointer").build();
+        let decoded = Message::builder().message_id("m1").body("not json").build();
+        let ack = decoding.handle_received(&received, &decoded).await.unwrap();
+        assert_eq!(ack, Ack::Delete);
+        let calls = decoding.poison.0.lock().unwrap();
+        assert_eq!(calls.len(), 1);
+        assert!(calls[0].starts_with("pointer: Json("), "{calls:?}");
+    }
+}
@@ -0,0 +1,362 @@
+//! Pre-send checks: message size, allowed characters and FIFO ids are validated
+//! locally so limits fail with a precise error instead of an opaque SDK error.
+
+use std::fmt;
+
+use crate::attrs;
+use crate::batch::BatchEntry;
+
+/// Largest message SQS accepts: body plus attribute names, types and values.
+pub const MAX_MESSAGE_BYTES: usize = 256 * 1024;
+/// MessageGroupId and MessageDeduplicationId are limited to 128 characters.
+pub const MAX_ID_LEN: usize = 128;
+
+/// Queue kind a message is validated for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueueKind {
+    Standard,
+    Fifo,
+}
+
+impl QueueKind {
+    pub fn from_name(queue_name: &str) -> Self {
+        if queue_name.ends_with(".fifo") {
+            Self::Fifo
+        } else {
+            Self::Standard
+        }
+    }
+}
+
+/// FIFO id a validation error refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdField {
+    GroupId,
+    DedupId,
+}
+
+impl fmt::Display for IdField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::GroupId => "MessageGroupId",
+            Self::DedupId => "MessageDeduplicationId",
+        })
+    }
+}
+
+/// Why a message would be rejected by SQS.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    EmptyBody,
+    /// Body plus attributes exceed the size limit
+    TooLarge {
+        size: usize,
+        body: usize,
+        attributes: usize,
+        limit: usize,
+    },
+    /// A character outside the XML range SQS allows; `position` is a char index
+    InvalidChar {
+        field: String,
+        position: usize,
+        ch: char,
+    },
+    /// Attribute count, name or type rejected (see [`attrs::validate`])
+    Attribute(String),
+    /// FIFO queue without a MessageGroupId
+    MissingGroupId,
+    /// MessageDeduplicationId on a standard queue
+    FifoOnly(IdField),
+    IdLength {
+        field: IdField,
+        len: usize,
+    },
+    IdInvalidChar {
+        field: IdField,
+        position: usize,
+        ch: char,
+    },
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EmptyBody => write!(f, "message body cannot be empty"),
+            Self::TooLarge {
+                size,
+                body,
+                attributes,
+                limit,
+            } => write!(
+                f,
+                "message is {} bytes (body {} + attributes {}), limit is {} bytes",
+                size, body, attributes, limit
+            ),
+            Self::InvalidChar {
+                field,
+                position,
+                ch,
+            } => write!(
+                f,
+                "{} contains disallowed character U+{:04X} at position {}",
+                field, *ch as u32, position
+            ),
+            Self::Attribute(reason) => write!(f, "{}", reason),
+            Self::MissingGroupId => write!(f, "FIFO queues require a MessageGroupId"),
+            Self::FifoOnly(field) => write!(f, "{} is only valid on FIFO queues", field),
+            Self::IdLength { field, len } => write!(
+                f,
+                "{} must be 1..={} characters, got {}",
+                field, MAX_ID_LEN, len
+            ),
+            Self::IdInvalidChar {
+                field,
+                position,
+                ch,
+            } => write!(
+                f,
+                "{} contains {:?} at position {}; only ASCII letters, digits and punctuation are allowed",
+                field, ch, position
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// Characters SQS accepts in bodies and string attribute values:
+/// #x9 | #xA | #xD | #x20-#xD7FF | #xE000-#xFFFD | #x10000-#x10FFFF.
+pub fn is_allowed_char(c: char) -> bool {
+    matches!(c, '\t' | '\n' | '\r' | '\u{20}'..='\u{D7FF}' | '\u{E000}'..='\u{FFFD}' | '\u{10000}'..)
+}
+
+fn check_chars(field: impl FnOnce() -> String, text: &str) -> Result<(), ValidationError> {
+    match text.chars().enumerate().find(|(_, c)| !is_allowed_char(*c)) {
+        Some((position, ch)) => Err(ValidationError::InvalidChar {
+            field: field(),
+            position,
+            ch,
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Check a MessageGroupId / MessageDeduplicationId: 1..=128 ASCII letters, digits and punctuation.
+pub fn check_id(field: IdField, id: &str) -> Result<(), ValidationError> {
+    let len = id.chars().count();
+    if len == 0 || len > MAX_ID_LEN {
+        return Err(ValidationError::IdLength { field, len });
+    }
+    match id.chars().enumerate().find(|(_, c)| !c.is_ascii_graphic()) {
+        Some((position, ch)) => Err(ValidationError::IdInvalidChar {
+            field,
+            position,
+            ch,
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Validate one outgoing message against the SQS limits for `kind`.
+pub fn check_entry(entry: &BatchEntry, kind: QueueKind) -> Result<(), ValidationError> {
+    check_entry_max(entry, kind, MAX_MESSAGE_BYTES)
+}
+
+/// [`check_entry`] with a different size limit, e.g. `usize::MAX` when oversized
+/// bodies are offloaded to S3.
+pub fn check_entry_max(
+    entry: &BatchEntry,
+    kind: QueueKind,
+    max_bytes: usize,
+) -> Result<(), ValidationError> {
+    if entry.body.is_empty() {
+        return Err(ValidationError::EmptyBody);
+    }
+    check_chars(|| "body".to_string(), &entry.body)?;
+
+    attrs::validate(&entry.attributes).map_err(|e| ValidationError::Attribute(format!("{e:#}")))?;
+    for (name, value) in &entry.attributes {
+        if let Some(s) = value.string_value() {
+            check_chars(|| format!("attribute '{}'", name), s)?;
+        }
+    }
+
+    let size = entry.size();
+    if size > max_bytes {
+        return Err(ValidationError::TooLarge {
+            size,
+            body: entry.body.len(),
+            attributes: size - entry.body.len(),
+            limit: max_bytes,
+        });
+    }
+
+    match kind {
+        QueueKind::Fifo => {
+            let group = entry
+                .group_id
+                .as_deref()
+                .ok_or(ValidationError::MissingGroupId)?;
+            check_id(IdField::GroupId, group)?;
+            if let Some(dedup) = &entry.dedup_id {
+                check_id(IdField::DedupId, dedup)?;
+            }
+        }
+        QueueKind::Standard => {
+            // Standard queues accept a MessageGroupId (fair queues) but no dedup id
+            if let Some(group) = &entry.group_id {
+                check_id(IdField::GroupId, group)?;
+            }
+            if entry.dedup_id.is_some() {
+                return Err(ValidationError::FifoOnly(IdField::DedupId));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Validate every entry of a batch; the error names the first offending entry.
+pub fn check_entries(
+    entries: &[BatchEntry],
+    kind: QueueKind,
+    max_bytes: usize,
+) -> anyhow::Result<()> {
+    for (i, e) in entries.iter().enumerate() {
+        check_entry_max(e, kind, max_bytes)
+            .map_err(|err| anyhow::Error::new(err).context(format!("entry #{i}")))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use aws_sdk_sqs::types::MessageAttributeValue;
+
+    use super::*;
+
+    fn entry(body: &str) -> BatchEntry {
+        BatchEntry {
+            body: body.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn with_attrs(body: &str, attrs: &[(&str, &str)]) -> BatchEntry {
+        let mut e = entry(body);
+        for (k, v) in attrs {
+            let value = MessageAttributeValue::builder()
+                .data_type("String")
+                .string_value(*v)
+                .build()
+                .unwrap();
+            e.attributes.insert(k.to_string(), value);
+        }
+        e
+    }
+
+    #[test]
+    fn size_counts_body_and_attributes() {
+        // "k" + "String" + "vv" = 9 attribute bytes
+        let attrs = [("k", "vv")];
+        let at_limit = with_attrs(&"x".repeat(MAX_MESSAGE_BYTES - 9), &attrs);
+        assert_eq!(check_entry(&at_limit, QueueKind::Standard), Ok(()));
+
+        let over = with_attrs(&"x".repeat(MAX_MESSAGE_BYTES - 8), &attrs);
+        assert_eq!(
+            check_entry(&over, QueueKind::Standard),
+            Err(ValidationError::TooLarge {
+                size: MAX_MESSAGE_BYTES + 1,
+                body: MAX_MESSAGE_BYTES - 8,
+                attributes: 9,
+                limit: MAX_MESSAGE_BYTES,
+            })
+        );
+        assert_eq!(
+            check_entry_max(&over, QueueKind::Standard, usize::MAX),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn body_content() {
+        assert_eq!(
+            check_entry(&entry(""), QueueKind::Standard),
+            Err(ValidationError::EmptyBody)
+        );
+        assert_eq!(
+            check_entry(&entry("ab\u{1}c"), QueueKind::Standard),
+            Err(ValidationError::InvalidChar {
+                field: "body".to_string(),
+                position: 2,
+                ch: '\u{1}',
+            })
+        );
+        assert_eq!(
+            check_entry(&entry("tab\tand\nnewline ✓"), QueueKind::Standard),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn attribute_count_limit() {
+        let names: Vec<String> = (0..=attrs::MAX_ATTRIBUTES)
+            .map(|i| format!("a{i}"))
+            .collect();
+        let pairs: Vec<(&str, &str)> = names.iter().map(|n| (n.as_str(), "v")).collect();
+
+        let ten = with_attrs("body", &pairs[..attrs::MAX_ATTRIBUTES]);
+        assert_eq!(check_entry(&ten, QueueKind::Standard), Ok(()));
+
+        let eleven = with_attrs("body", &pairs);
+        assert!(matches!(
+            check_entry(&eleven, QueueKind::Standard),
+            Err(ValidationError::Attribute(_))
+        ));
+    }
+
+    #[test]
+    fn fifo_ids() {
+        let mut e = entry("body");
+        assert_eq!(
+            check_entry(&e, QueueKind::Fifo),
+            Err(ValidationError::MissingGroupId)
+        );
+
+        e.group_id = Some("g".repeat(MAX_ID_LEN));
+        assert_eq!(check_entry(&e, QueueKind::Fifo), Ok(()));
+
+        e.group_id = Some("g".repeat(MAX_ID_LEN + 1));
+        assert_eq!(
+            check_entry(&e, QueueKind::Fifo),
+            Err(ValidationError::IdLength {
+                field: IdField::GroupId,
+                len: MAX_ID_LEN + 1,
+            })
+        );
+
+        e.group_id = Some("g1".to_string());
+        e.dedup_id = Some("has space".to_string());
+        assert_eq!(
+            check_entry(&e, QueueKind::Fifo),
+            Err(ValidationError::IdInvalidChar {
+                field: IdField::DedupId,
+                position: 3,
+                ch: ' ',
+            })
+        );
+
+        e.dedup_id = Some("d1".to_string());
+        assert_eq!(check_entry(&e, QueueKind::Fifo), Ok(()));
+        assert_eq!(
+            check_entry(&e, QueueKind::Standard),
+            Err(ValidationError::FifoOnly(IdField::DedupId))
+        );
+    }
+
+    #[test]
+    fn entries_error_names_the_entry() {
+        let entries = [entry("ok"), entry("")];
+        let err = check_entries(&entries, QueueKind::Standard, MAX_MESSAGE_BYTES).unwrap_err();
+        assert_eq!(err.to_string(), "entry #1");
+    }
+}